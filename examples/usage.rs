@@ -22,8 +22,10 @@ fn main() {
 
     // Run the algorithm.
     // This will fill in the `x` and `y` components of the rectangles
-    // present in `subjects`, and return the resulting bin size.
-    let bin_size = find_best_packing(
+    // present in `subjects`, and return the resulting bin size, along with
+    // what happened to every rectangle and some statistics about the search.
+    // It only fails for unusable input, e.g. negative sizes or nothing to pack.
+    let result = find_best_packing(
         &mut root,
         subjects.iter_mut(),
         &Input::new(
@@ -32,8 +34,10 @@ fn main() {
             |_| CallbackResult::ContinuePacking,
             |_| CallbackResult::AbortPacking,
         ),
-    );
+    )
+    .expect("the input is valid");
 
-    println!("Bin size: {:?}", bin_size);
+    println!("Bin size: {:?}", result.bin);
+    println!("Occupancy: {:.1}%", result.occupancy * 100.0);
     println!("Subjects: {:?}", subjects);
 }
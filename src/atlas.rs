@@ -0,0 +1,205 @@
+use crate::{
+    coord::Coord,
+    empty_spaces::{EmptySpaces, EmptySpacesProviderTrait, Padding},
+    error::PackError,
+    rect_structs::{RectWH, RectXYWH, TotalAreaType},
+};
+
+/// Identifies a rectangle allocated in an `Atlas`.
+/// Handles of deallocated rectangles are never valid again,
+/// even if their slot gets reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot<T: Coord> {
+    generation: u32,
+    allocation: Option<Allocation<T>>,
+}
+
+#[derive(Clone, Copy)]
+struct Allocation<T: Coord> {
+    /// What the caller gets to see.
+    rect: RectXYWH<T>,
+    /// What was taken out of the free space, i.e. `rect` and its spacing.
+    region: RectXYWH<T>,
+}
+
+/// A long-lived bin that rectangles can be allocated in and freed from one by one,
+/// for caches that can't afford to repack everything whenever something changes.
+///
+/// Freed rectangles are merged with the free spaces they share a whole edge with,
+/// so that the space can be reused by bigger rectangles later. Spaces that only
+/// partly touch aren't merged, so the free space can fragment over time
+/// until the next `clear`.
+pub struct Atlas<EmptySpacesProvider: EmptySpacesProviderTrait<T>, T: Coord = i32> {
+    size: RectWH<T>,
+    spaces: EmptySpaces<EmptySpacesProvider, T>,
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<usize>,
+    used_area: TotalAreaType<T>,
+    count: usize,
+}
+
+impl<EmptySpacesProvider: EmptySpacesProviderTrait<T>, T: Coord> Atlas<EmptySpacesProvider, T> {
+    /// Creates an empty atlas of the given size.
+    pub fn new(size: RectWH<T>) -> Self {
+        Self {
+            size,
+            spaces: EmptySpaces::new(size),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            used_area: Default::default(),
+            count: 0,
+        }
+    }
+
+    /// Sets the spacing between rectangles and the margin from the atlas border.
+    /// Clears the atlas, invalidating every handle.
+    pub fn with_padding(mut self, padding: Padding<T>) -> Self {
+        self.spaces.set_padding(padding);
+        self.clear();
+        self
+    }
+
+    /// Allocates a `w` by `h` rectangle. Fails with `PackError::DidNotFit` if there's no room for it.
+    pub fn allocate(&mut self, w: T, h: T) -> Result<Handle, PackError> {
+        let rect: RectXYWH<T> = self.spaces.insert(RectWH::new(w, h))?;
+        let spacing = self.spaces.get_padding().spacing;
+
+        let allocation = Allocation {
+            rect,
+            region: RectXYWH::new(rect.x, rect.y, rect.w + spacing, rect.h + spacing),
+        };
+
+        let index = match self.free_slots.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot::default());
+                self.slots.len() - 1
+            }
+        };
+
+        let slot = &mut self.slots[index];
+        slot.allocation = Some(allocation);
+
+        self.used_area += rect.area();
+        self.count += 1;
+
+        Ok(Handle {
+            index,
+            generation: slot.generation,
+        })
+    }
+
+    /// Gives the space of `handle` back, and returns where it was.
+    /// Returns `Ok(None)` if `handle` was already deallocated.
+    ///
+    /// Fails with `PackError::ProviderCapacityExceeded` if the provider runs out of room
+    /// for the freed space. The space is then lost until the next `clear`, but the handle
+    /// is deallocated nevertheless.
+    pub fn deallocate(&mut self, handle: Handle) -> Result<Option<RectXYWH<T>>, PackError> {
+        let Some(slot) = self
+            .slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+        else {
+            return Ok(None);
+        };
+
+        let Some(allocation) = slot.allocation.take() else {
+            return Ok(None);
+        };
+
+        slot.generation = slot.generation.wrapping_add(1);
+
+        self.free_slots.push(handle.index);
+        self.used_area -= allocation.rect.area();
+        self.count -= 1;
+
+        self.spaces.free(allocation.region)?;
+
+        Ok(Some(allocation.rect))
+    }
+
+    /// Where the rectangle of `handle` is, or `None` if it was deallocated.
+    pub fn get(&self, handle: Handle) -> Option<RectXYWH<T>> {
+        self.slots
+            .get(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.allocation)
+            .map(|allocation| allocation.rect)
+    }
+
+    /// Iterates over every allocated rectangle, along with its handle.
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, RectXYWH<T>)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.allocation.map(|allocation| {
+                (
+                    Handle {
+                        index,
+                        generation: slot.generation,
+                    },
+                    allocation.rect,
+                )
+            })
+        })
+    }
+
+    /// Deallocates everything, invalidating every handle.
+    pub fn clear(&mut self) {
+        self.spaces.reset(self.size);
+        self.free_slots.clear();
+
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.allocation.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+
+            self.free_slots.push(index);
+        }
+
+        // Hand out the lowest indices first.
+        self.free_slots.reverse();
+
+        self.used_area = Default::default();
+        self.count = 0;
+    }
+
+    pub fn size(&self) -> RectWH<T> {
+        self.size
+    }
+
+    /// The number of allocated rectangles.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The total area of the allocated rectangles, not including any padding.
+    pub fn used_area(&self) -> TotalAreaType<T> {
+        self.used_area
+    }
+
+    /// The ratio of `used_area` to the area of the atlas, from 0 to 1.
+    pub fn occupancy(&self) -> f64 {
+        let total = T::area_to_f64(self.size.area());
+
+        if total > 0.0 {
+            T::area_to_f64(self.used_area) / total
+        } else {
+            0.0
+        }
+    }
+
+    /// The underlying free space, e.g. for drawing it.
+    pub fn get_spaces(&self) -> &EmptySpacesProvider {
+        self.spaces.get_spaces()
+    }
+}
@@ -1,7 +1,12 @@
+use std::{cmp::Ordering, num::NonZeroUsize, panic, thread};
+
 use crate::{
-    empty_spaces::{EmptySpaces, EmptySpacesProviderTrait},
-    finders_interface::Input,
-    rect_structs::{RectWH, RectXYWH, TotalAreaType},
+    coord::{self, Coord},
+    error::PackError,
+    finders_interface::{BinStats, Input, MultiBinPacking, PackingResult, RectStatus},
+    insert_and_split::SplitRule,
+    packer::Packer,
+    rect_structs::{OutputRect, RectWH, RectXYWH, TotalAreaType},
 };
 
 pub enum CallbackResult {
@@ -9,25 +14,245 @@ pub enum CallbackResult {
     ContinuePacking,
 }
 
+/// A rectangle to pack, along with the padding it needs around itself.
+/// `index` is its position in the caller's input.
+#[derive(Clone, Copy)]
+pub(crate) struct Subject<T: Coord> {
+    pub index: usize,
+    pub wh: RectWH<T>,
+    pub padding: T,
+    /// What the subject is worth when packing by value.
+    pub value: f64,
+}
+
+impl<T: Coord> Subject<T> {
+    /// The value per unit of area.
+    fn density(&self) -> f64 {
+        self.value / T::area_to_f64(self.wh.area())
+    }
+}
+
+/// Copies `subjects` into one chunk per orderer,
+/// and sorts every chunk in descending order.
+/// With `by_value`, the chunks sorted by `value_orderers` are added at the end.
+pub(crate) fn make_orders<T: Coord>(
+    subjects: &[Subject<T>],
+    orderers: &[impl Fn(&Subject<T>, &Subject<T>) -> Ordering],
+    by_value: bool,
+) -> Box<[Subject<T>]> {
+    let mut orders = sorted_chunks(subjects, orderers);
+
+    if by_value {
+        orders.extend(sorted_chunks(subjects, &value_orderers()));
+    }
+
+    orders.into_boxed_slice()
+}
+
+fn sorted_chunks<T: Coord>(
+    subjects: &[Subject<T>],
+    orderers: &[impl Fn(&Subject<T>, &Subject<T>) -> Ordering],
+) -> Vec<Subject<T>> {
+    let mut orders = subjects.repeat(orderers.len());
+
+    if !subjects.is_empty() {
+        for (chunk, o) in orders.chunks_exact_mut(subjects.len()).zip(orderers) {
+            chunk.sort_by(|l, r| o(l, r).reverse());
+        }
+    }
+
+    orders
+}
+
+type SubjectOrderer<T> = fn(&Subject<T>, &Subject<T>) -> Ordering;
+
+/// The orderings tried on top of the caller's ones when packing by value:
+/// by value, and by value per unit of area.
+fn value_orderers<T: Coord>() -> [SubjectOrderer<T>; 2] {
+    [
+        |l, r| l.value.total_cmp(&r.value),
+        |l, r| l.density().total_cmp(&r.density()),
+    ]
+}
+
+/// Constraints on the size of the resulting bin.
+/// Only bins that satisfy them are tried during the search.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinSizePolicy<T: Coord = i32> {
+    /// Any size is allowed.
+    #[default]
+    Free,
+    /// Both sides must be powers of two.
+    PowerOfTwo,
+    /// Both sides must be the same power of two.
+    SquarePowerOfTwo,
+    /// Both sides must be multiples of the given value.
+    MultipleOf(T),
+}
+
+impl<T: Coord> BinSizePolicy<T> {
+    /// Rounds `bin` up to the nearest allowed size.
+    pub fn round_up(self, bin: RectWH<T>) -> RectWH<T> {
+        match self {
+            Self::Free => bin,
+            Self::PowerOfTwo => RectWH::new(bin.w.round_up_pow2(), bin.h.round_up_pow2()),
+            Self::SquarePowerOfTwo => {
+                let side = bin.max_side().round_up_pow2();
+                RectWH::new(side, side)
+            }
+            Self::MultipleOf(n) => {
+                RectWH::new(bin.w.round_up_to_multiple(n), bin.h.round_up_to_multiple(n))
+            }
+        }
+    }
+
+    /// Rounds `bin` down to the nearest allowed size.
+    pub fn round_down(self, bin: RectWH<T>) -> RectWH<T> {
+        match self {
+            Self::Free => bin,
+            Self::PowerOfTwo => RectWH::new(bin.w.round_down_pow2(), bin.h.round_down_pow2()),
+            Self::SquarePowerOfTwo => {
+                let side = bin.min_side().round_down_pow2();
+                RectWH::new(side, side)
+            }
+            Self::MultipleOf(n) => RectWH::new(
+                bin.w.round_down_to_multiple(n),
+                bin.h.round_down_to_multiple(n),
+            ),
+        }
+    }
+
+    /// Rounds `bin` up, or down if rounding up would exceed `limit`.
+    /// Rounding down can give a bin smaller than `bin`, so this is only
+    /// meant for sizes to try, never for the size of what was already placed.
+    pub fn constrain(self, bin: RectWH<T>, limit: RectWH<T>) -> RectWH<T> {
+        let up = self.round_up(bin);
+
+        if up.w <= limit.w && up.h <= limit.h {
+            up
+        } else {
+            self.round_down(bin)
+        }
+    }
+}
+
+/// Which sides of the bin the search shrinks.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SearchMode {
+    /// Shrinks both sides, then each one on its own.
+    #[default]
+    Both,
+    /// Strip packing: the width stays at that of the maximum bin,
+    /// and only the height is minimized. `Skyline` is built for this,
+    /// and often packs strips tighter than the other packers.
+    FixedWidth,
+    /// Like `FixedWidth`, but the height stays, and the width is minimized.
+    FixedHeight,
+}
+
+impl SearchMode {
+    /// Puts the pinned side of `max_bin`, if any, into `bin`.
+    fn pin<T: Coord>(self, bin: RectWH<T>, max_bin: RectWH<T>) -> RectWH<T> {
+        match self {
+            Self::Both => bin,
+            Self::FixedWidth => RectWH::new(max_bin.w, bin.h),
+            Self::FixedHeight => RectWH::new(bin.w, max_bin.h),
+        }
+    }
+}
+
+/// The parts of `Input` that the search itself needs.
+#[derive(Clone, Copy)]
+pub(crate) struct SearchSettings<'a, T: Coord> {
+    pub max_bin: RectWH<T>,
+    pub discard_step: i32,
+    pub bin_size_policy: BinSizePolicy<T>,
+    pub parallel: bool,
+    pub reserved: &'a [RectXYWH<T>],
+    pub mode: SearchMode,
+    pub by_value: bool,
+}
+
+impl<'a, F, G, T: Coord> From<&'a Input<F, G, T>> for SearchSettings<'a, T> {
+    fn from(input: &'a Input<F, G, T>) -> Self {
+        Self {
+            // The search never tries anything the policy doesn't allow.
+            max_bin: input.bin_size_policy.round_down(input.max_bin),
+            discard_step: input.discard_step,
+            bin_size_policy: input.bin_size_policy,
+            parallel: input.parallel,
+            reserved: &input.reserved,
+            mode: input.search_mode,
+            by_value: !input.values.is_empty(),
+        }
+    }
+}
+
+impl<T: Coord> SearchSettings<'_, T> {
+    /// The size of the bin that ends up containing `aabb`, i.e. `aabb` rounded up.
+    /// If that exceeds `max_bin`, it's `max_bin` instead, and the second value is false,
+    /// meaning that whatever sticks out of it has to be taken back out.
+    fn final_bin(&self, aabb: RectWH<T>) -> (RectWH<T>, bool) {
+        let bin = self
+            .bin_size_policy
+            .round_up(self.mode.pin(aabb, self.max_bin));
+
+        if bin.w <= self.max_bin.w && bin.h <= self.max_bin.h {
+            (bin, true)
+        } else {
+            (self.max_bin, false)
+        }
+    }
+}
+
+/// Resets `root` to `bin` and cuts the reserved regions out of it.
+/// Returns false if `bin` doesn't contain all of them.
+fn reset_reserved<T: Coord>(
+    root: &mut impl Packer<T>,
+    bin: RectWH<T>,
+    reserved: &[RectXYWH<T>],
+) -> Result<bool, PackError> {
+    root.reset(bin);
+
+    for &region in reserved {
+        if !RectXYWH::from_wh(bin.w, bin.h).contains(region) {
+            return Ok(false);
+        }
+
+        root.reserve(region)?;
+    }
+
+    Ok(true)
+}
+
 pub(crate) enum BinDimension {
     Both,
     Width,
     Height,
 }
 
-pub(crate) enum BestPackingReturn {
-    TotalArea(TotalAreaType),
-    Rect(RectWH),
+pub(crate) enum BestPackingReturn<T: Coord> {
+    TotalArea(TotalAreaType<T>),
+    /// What replaces `TotalArea` when packing by value, see `packed_value`.
+    Value(f64),
+    Rect(RectWH<T>),
 }
 
-fn best_packing_for_ordering_impl(
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    ordering: &[*mut RectXYWH],
-    starting_bin: RectWH,
-    mut discard_step: i32,
+/// Binary searches for the smallest bin that fits `ordering`, shrinking the dimensions
+/// given by `tried_dimension`. Every dimension is stepped through on its own,
+/// so the search never leaves `starting_bin` on either axis.
+fn best_packing_for_ordering_impl<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    starting_bin: RectWH<T>,
+    settings: SearchSettings<'_, T>,
     tried_dimension: BinDimension,
-) -> BestPackingReturn {
+    packings: &mut usize,
+) -> BestPackingReturn<T> {
     let mut candidate_bin = starting_bin;
+    let mut discard_step = settings.discard_step;
     let mut tries_before_discarding = 0;
 
     if discard_step <= 0 {
@@ -35,165 +260,558 @@ fn best_packing_for_ordering_impl(
         discard_step = 1
     }
 
+    let discard_step = T::from_i32(discard_step);
+    let two = T::TWO;
+
     let starting_step = match tried_dimension {
         BinDimension::Both => {
-            candidate_bin.w /= 2;
-            candidate_bin.h /= 2;
+            candidate_bin.w = candidate_bin.w / two;
+            candidate_bin.h = candidate_bin.h / two;
 
-            candidate_bin.w / 2
+            RectWH::new(candidate_bin.w / two, candidate_bin.h / two)
         }
         BinDimension::Width => {
-            candidate_bin.w /= 2;
+            candidate_bin.w = candidate_bin.w / two;
 
-            candidate_bin.w / 2
+            RectWH::new(candidate_bin.w / two, T::ZERO)
         }
         BinDimension::Height => {
-            candidate_bin.h /= 2;
+            candidate_bin.h = candidate_bin.h / two;
 
-            candidate_bin.h / 2
+            RectWH::new(T::ZERO, candidate_bin.h / two)
         }
     };
 
     let mut step = starting_step;
     loop {
-        root.reset(candidate_bin);
-        let mut total_inserted_area = 0;
+        let constrained_bin = settings
+            .bin_size_policy
+            .constrain(candidate_bin, starting_bin);
+
+        let mut total_inserted_area = TotalAreaType::<T>::default();
+        *packings += 1;
 
-        if all_inserted(ordering, root, &mut total_inserted_area) {
-            if step <= discard_step {
+        if reset_reserved(root, constrained_bin, settings.reserved).unwrap_or(false)
+            && all_inserted::<T, R>(ordering, root, &mut total_inserted_area)
+        {
+            if step.max_side() <= discard_step {
                 if tries_before_discarding > 0 {
                     tries_before_discarding -= 1;
                 } else {
-                    return BestPackingReturn::Rect(candidate_bin);
+                    return BestPackingReturn::Rect(constrained_bin);
                 }
             }
 
-            match tried_dimension {
-                BinDimension::Both => {
-                    candidate_bin.w -= step;
-                    candidate_bin.h -= step;
-                }
-                BinDimension::Width => {
-                    candidate_bin.w -= step;
-                }
-                BinDimension::Height => {
-                    candidate_bin.h -= step;
-                }
-            }
+            candidate_bin.w -= step.w;
+            candidate_bin.h -= step.h;
 
             root.reset(candidate_bin);
         } else {
-            match tried_dimension {
-                BinDimension::Both => {
-                    candidate_bin.w += step;
-                    candidate_bin.h += step;
+            candidate_bin.w += step.w;
+            candidate_bin.h += step.h;
 
-                    if candidate_bin.area() > starting_bin.area() {
-                        return BestPackingReturn::TotalArea(total_inserted_area);
-                    }
-                }
-                BinDimension::Width => {
-                    candidate_bin.w += step;
+            if candidate_bin.w > starting_bin.w || candidate_bin.h > starting_bin.h {
+                return BestPackingReturn::TotalArea(total_inserted_area);
+            }
+        }
 
-                    if candidate_bin.w > starting_bin.w {
-                        return BestPackingReturn::TotalArea(total_inserted_area);
-                    }
-                }
-                BinDimension::Height => {
-                    candidate_bin.h += step;
+        let one = T::ONE;
 
-                    if candidate_bin.h > starting_bin.h {
-                        return BestPackingReturn::TotalArea(total_inserted_area);
-                    }
-                }
+        step = match tried_dimension {
+            BinDimension::Both => {
+                RectWH::new(coord::max(one, step.w / two), coord::max(one, step.h / two))
             }
-        }
+            BinDimension::Width => RectWH::new(coord::max(one, step.w / two), T::ZERO),
+            BinDimension::Height => RectWH::new(T::ZERO, coord::max(one, step.h / two)),
+        };
+    }
+}
 
-        step = 1.max(step / 2);
+/// Searches for the best bin for `ordering`. If it doesn't fit even `starting_bin`
+/// and the search is by value, the result is what `packed_value` gives.
+fn best_packing_for_ordering<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    starting_bin: RectWH<T>,
+    settings: SearchSettings<'_, T>,
+    packings: &mut usize,
+) -> BestPackingReturn<T> {
+    match best_bin_for_ordering::<T, R>(root, ordering, starting_bin, settings, packings) {
+        BestPackingReturn::TotalArea(_) if settings.by_value => {
+            *packings += 1;
+
+            BestPackingReturn::Value(packed_value::<T, R>(root, ordering, starting_bin, settings))
+        }
+        result => result,
     }
 }
 
-fn best_packing_for_ordering(
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    ordering: &[*mut RectXYWH],
-    starting_bin: RectWH,
-    discard_step: i32,
-) -> BestPackingReturn {
-    let mut best_result = try_pack(
+fn best_bin_for_ordering<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    starting_bin: RectWH<T>,
+    settings: SearchSettings<'_, T>,
+    packings: &mut usize,
+) -> BestPackingReturn<T> {
+    match settings.mode {
+        SearchMode::Both => {}
+        SearchMode::FixedWidth => {
+            return try_pack::<T, R>(
+                root,
+                ordering,
+                starting_bin,
+                settings,
+                BinDimension::Height,
+                packings,
+            );
+        }
+        SearchMode::FixedHeight => {
+            return try_pack::<T, R>(
+                root,
+                ordering,
+                starting_bin,
+                settings,
+                BinDimension::Width,
+                packings,
+            );
+        }
+    }
+
+    let mut best_result = try_pack::<T, R>(
         root,
         ordering,
         starting_bin,
-        discard_step,
+        settings,
         BinDimension::Both,
+        packings,
     );
 
     if let BestPackingReturn::Rect(r) = &mut best_result {
-        trial(root, ordering, r, discard_step, BinDimension::Width);
-        trial(root, ordering, r, discard_step, BinDimension::Height);
+        trial::<T, R>(root, ordering, r, settings, BinDimension::Width, packings);
+        trial::<T, R>(root, ordering, r, settings, BinDimension::Height, packings);
     }
 
     best_result
 }
 
+/// The total value of what fits into `bin`, skipping what doesn't,
+/// the same way the final placement does it.
+fn packed_value<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    bin: RectWH<T>,
+    settings: SearchSettings<'_, T>,
+) -> f64 {
+    if !reset_reserved(root, bin, settings.reserved).unwrap_or(false) {
+        return 0.0;
+    }
+
+    ordering
+        .iter()
+        .filter(|r| root.insert_padded::<R>(r.wh, r.padding).is_ok())
+        .map(|r| r.value)
+        .sum()
+}
+
+/// Runs the search over `orders`, then places the subjects of the best one.
+/// `subject_count` is the number of rectangles the caller passed in,
+/// including the ones that aren't subjects, and `original` gives the one
+/// at an index, for reporting it to `handle_unsuccessful_insertion`.
+///
+/// Fails if the provider runs out of room during the final placement,
+/// since the packing would silently be worse than the search said.
 pub(crate) fn find_best_packing_impl<
-    EST: EmptySpacesProviderTrait,
-    F: Fn(RectXYWH) -> CallbackResult,
-    G: Fn(RectXYWH) -> CallbackResult,
+    PT: Packer<T>,
+    T: Coord,
+    R: OutputRect<T>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
 >(
-    root: &mut EmptySpaces<EST>,
-    orders: &[*mut RectXYWH],
+    root: &mut PT,
+    orders: &[Subject<T>],
     chunk_len: usize,
-    input: &Input<F, G>,
-) -> RectWH {
-    let max_bin = RectWH::new(input.max_bin_side, input.max_bin_side);
-
-    let mut best_order: Option<&[*mut RectXYWH]> = None;
-    let mut best_total_inserted = -1;
-    let mut best_bin = max_bin;
-
-    for order in orders.chunks_exact(chunk_len) {
-        for_each_order_lambda(
-            root,
-            order,
-            max_bin,
-            input.discard_step,
-            &mut best_order,
-            &mut best_total_inserted,
-            &mut best_bin,
-        );
-    }
-
-    root.reset(best_bin);
-
-    for rr in best_order.unwrap().iter() {
-        let rect = unsafe { &mut **rr };
-        match root.insert(rect.into()) {
-            Some(ret) => {
-                *rect = ret;
-                if let CallbackResult::AbortPacking = (input.handle_successful_insertion)(*rect) {
+    input: &Input<F, G, T>,
+    subject_count: usize,
+    original: impl Fn(usize) -> R,
+) -> Result<PackingResult<R, T>, PackError> {
+    let settings = SearchSettings::from(input);
+    root.set_padding(input.padding);
+
+    let best = find_best_order::<PT, T, R>(root, orders, chunk_len, settings, &input.split_rules)?;
+
+    reset_reserved(root, best.bin, settings.reserved)?;
+
+    let best_order = &orders[best.index * chunk_len..][..chunk_len];
+    let mut placements = vec![None; subject_count];
+    let mut statuses = vec![RectStatus::Skipped; subject_count];
+    let mut used_area = TotalAreaType::<T>::default();
+
+    for rr in best_order {
+        statuses[rr.index] = RectStatus::Failed;
+    }
+
+    for rr in best_order {
+        match root.insert_padded(rr.wh, rr.padding) {
+            Ok(ret) => {
+                placements[rr.index] = Some(ret);
+                statuses[rr.index] = RectStatus::Placed;
+                used_area += rr.wh.area();
+
+                if let CallbackResult::AbortPacking = (input.handle_successful_insertion)(ret) {
                     break;
                 }
             }
-            None => {
-                if let CallbackResult::AbortPacking = (input.handle_unsuccessful_insertion)(*rect) {
+            Err(PackError::DidNotFit) => {
+                if let CallbackResult::AbortPacking =
+                    (input.handle_unsuccessful_insertion)(original(rr.index))
+                {
                     break;
                 }
             }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let (bin, fits) = settings.final_bin(root.get_rects_aabb());
+
+    if !fits {
+        for rr in best_order {
+            if let Some(placed) = placements[rr.index].filter(|p| !contains(bin, p)) {
+                placements[rr.index] = None;
+                statuses[rr.index] = RectStatus::Failed;
+                used_area -= placed.get_wh().area();
+            }
         }
     }
 
-    root.get_rects_aabb()
+    Ok(PackingResult {
+        bin,
+        placements,
+        statuses,
+        best_order: best.index,
+        used_area,
+        occupancy: occupancy(used_area, bin),
+        // The final placement counts as well.
+        trial_packings: best.packings + 1,
+    })
+}
+
+/// Packs `subjects` into as many bins as necessary, running the
+/// ordering search separately for each one. Whatever doesn't fit
+/// into a bin is carried over to the next one.
+///
+/// Fills in the placement of every placed subject. `placements` has an entry for
+/// every rectangle the caller passed in, including the ones that aren't subjects.
+/// `original` is as in `find_best_packing_impl`.
+pub(crate) fn find_best_packing_multi_impl<
+    PT: Packer<T>,
+    T: Coord,
+    R: OutputRect<T>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PT,
+    subjects: &[Subject<T>],
+    input: &Input<F, G, T>,
+    orderers: &[impl Fn(&Subject<T>, &Subject<T>) -> Ordering],
+    placements: &mut [Option<R>],
+    original: impl Fn(usize) -> R,
+) -> Result<MultiBinPacking<T>, PackError> {
+    let settings = SearchSettings::from(input);
+    root.set_padding(input.padding);
+
+    let mut packing = MultiBinPacking {
+        bins: Vec::new(),
+        pages: vec![None; placements.len()],
+        statuses: vec![RectStatus::Skipped; placements.len()],
+        stats: Vec::new(),
+    };
+    let mut remaining = subjects.to_vec();
+
+    while !remaining.is_empty() {
+        let chunk_len = remaining.len();
+        let orders = make_orders(&remaining, orderers, settings.by_value);
+        let best =
+            find_best_order::<PT, T, R>(root, &orders, chunk_len, settings, &input.split_rules)?;
+
+        reset_reserved(root, best.bin, settings.reserved)?;
+
+        let best_order = &orders[best.index * chunk_len..][..chunk_len];
+        let mut left_over = Vec::new();
+
+        let page = packing.bins.len();
+        let mut aborted = false;
+
+        for rr in best_order {
+            match root.insert_padded(rr.wh, rr.padding) {
+                Ok(ret) => {
+                    placements[rr.index] = Some(ret);
+                    packing.pages[rr.index] = Some(page);
+                    if let CallbackResult::AbortPacking = (input.handle_successful_insertion)(ret) {
+                        aborted = true;
+                        break;
+                    }
+                }
+                Err(PackError::DidNotFit) => left_over.push(*rr),
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Nothing fit into an empty bin, so nothing ever will.
+        if !aborted && left_over.len() == chunk_len {
+            for rr in left_over {
+                if let CallbackResult::AbortPacking =
+                    (input.handle_unsuccessful_insertion)(original(rr.index))
+                {
+                    break;
+                }
+            }
+
+            break;
+        }
+
+        let (bin, fits) = settings.final_bin(root.get_rects_aabb());
+
+        if !fits {
+            left_over.extend(take_back_outside(
+                bin,
+                best_order,
+                placements,
+                &mut packing.pages,
+                page,
+            ));
+        }
+
+        let used_area = best_order
+            .iter()
+            .filter(|rr| packing.pages[rr.index] == Some(page))
+            .fold(TotalAreaType::<T>::default(), |sum, rr| sum + rr.wh.area());
+
+        packing.bins.push(bin);
+        packing.stats.push(BinStats {
+            best_order: best.index,
+            used_area,
+            occupancy: occupancy(used_area, bin),
+            trial_packings: best.packings + 1,
+        });
+
+        if aborted {
+            break;
+        }
+
+        left_over.sort_unstable_by_key(|rr| rr.index);
+        remaining = left_over;
+    }
+
+    for rr in subjects {
+        packing.statuses[rr.index] = if packing.pages[rr.index].is_some() {
+            RectStatus::Placed
+        } else {
+            RectStatus::Failed
+        };
+    }
+
+    Ok(packing)
+}
+
+/// `used_area` divided by the area of `bin`, or 0 for an empty bin.
+fn occupancy<T: Coord>(used_area: TotalAreaType<T>, bin: RectWH<T>) -> f64 {
+    let bin_area = T::area_to_f64(bin.area());
+
+    if bin_area > 0.0 {
+        T::area_to_f64(used_area) / bin_area
+    } else {
+        0.0
+    }
+}
+
+/// Whether `placed` lies entirely inside `bin`.
+fn contains<T: Coord, R: OutputRect<T>>(bin: RectWH<T>, placed: &R) -> bool {
+    RectXYWH::from_wh(bin.w, bin.h).contains(placed.get_xywh())
 }
 
-fn all_inserted(
-    ordering: &[*mut RectXYWH],
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    total_inserted_area: &mut i32,
+/// Unplaces the subjects placed into bin `page` that stick out of `bin`, and returns them.
+fn take_back_outside<T: Coord, R: OutputRect<T>>(
+    bin: RectWH<T>,
+    subjects: &[Subject<T>],
+    placements: &mut [Option<R>],
+    pages: &mut [Option<usize>],
+    page: usize,
+) -> Vec<Subject<T>> {
+    let mut taken = Vec::new();
+
+    for rr in subjects {
+        let outside = placements[rr.index].is_some_and(|p| !contains(bin, &p));
+
+        if outside && pages[rr.index] == Some(page) {
+            placements[rr.index] = None;
+            pages[rr.index] = None;
+            taken.push(*rr);
+        }
+    }
+
+    taken
+}
+
+/// What `find_best_order` found.
+struct BestOrder<T: Coord> {
+    /// The index of the ordering among those in `orders`.
+    index: usize,
+    bin: RectWH<T>,
+    /// How many times the subjects were packed during the search.
+    packings: usize,
+}
+
+/// An ordering to search, and the split rule to search it with, if any.
+type Trial<'a, T> = (Option<SplitRule>, &'a [Subject<T>]);
+
+/// Runs the search for every ordering in `orders` and every rule in `split_rules`,
+/// and returns the index of the best ordering, along with the bin it fits into.
+/// The best split rule is set on `root`.
+fn find_best_order<PT: Packer<T>, T: Coord, R: OutputRect<T>>(
+    root: &mut PT,
+    orders: &[Subject<T>],
+    chunk_len: usize,
+    settings: SearchSettings<'_, T>,
+    split_rules: &[SplitRule],
+) -> Result<BestOrder<T>, PackError> {
+    if chunk_len == 0 {
+        return Err(PackError::NoRects);
+    }
+
+    let rules = if split_rules.is_empty() {
+        vec![None]
+    } else {
+        split_rules.iter().copied().map(Some).collect()
+    };
+
+    let trials = rules
+        .into_iter()
+        .flat_map(|rule| {
+            orders
+                .chunks_exact(chunk_len)
+                .map(move |order| (rule, order))
+        })
+        .collect::<Vec<Trial<T>>>();
+
+    let mut best_trial: Option<usize> = None;
+    let mut best_total_inserted = None;
+    let mut best_value = None;
+    let mut best_bin = settings.max_bin;
+    let mut packings = 0;
+
+    if settings.parallel {
+        let results = search_trials_parallel::<PT, T, R>(root.settings(), &trials, settings);
+
+        for (i, (result, trial_packings)) in results.into_iter().enumerate() {
+            packings += trial_packings;
+
+            for_each_order_lambda(
+                i,
+                result,
+                &mut best_trial,
+                &mut best_total_inserted,
+                &mut best_value,
+                &mut best_bin,
+            );
+        }
+    } else {
+        for (i, &(rule, order)) in trials.iter().enumerate() {
+            if let Some(rule) = rule {
+                root.set_split_rule(rule);
+            }
+
+            let result = best_packing_for_ordering::<T, R>(
+                root,
+                order,
+                settings.max_bin,
+                settings,
+                &mut packings,
+            );
+
+            for_each_order_lambda(
+                i,
+                result,
+                &mut best_trial,
+                &mut best_total_inserted,
+                &mut best_value,
+                &mut best_bin,
+            );
+        }
+    }
+
+    // The first trial always becomes the best one, unless the search failed outright.
+    let best_trial = best_trial.unwrap();
+
+    if let (Some(rule), _) = trials[best_trial] {
+        root.set_split_rule(rule);
+    }
+
+    Ok(BestOrder {
+        index: best_trial % (orders.len() / chunk_len),
+        bin: best_bin,
+        packings,
+    })
+}
+
+/// Searches every trial on a pool of scoped threads, each with its own packer.
+/// The results are returned in the same order as the trials, along with how many packings
+/// each one took, so that picking the best one gives the same answer as the sequential search.
+fn search_trials_parallel<PT: Packer<T>, T: Coord, R: OutputRect<T>>(
+    packer_settings: PT::Settings,
+    trials: &[Trial<T>],
+    settings: SearchSettings<'_, T>,
+) -> Vec<(BestPackingReturn<T>, usize)> {
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .clamp(1, trials.len().max(1));
+    let per_thread = trials.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles = trials
+            .chunks(per_thread)
+            .map(|group| {
+                scope.spawn(move || {
+                    let mut root = PT::from_settings(packer_settings);
+
+                    group
+                        .iter()
+                        .map(|&(rule, order)| {
+                            if let Some(rule) = rule {
+                                root.set_split_rule(rule);
+                            }
+
+                            let mut packings = 0;
+                            let result = best_packing_for_ordering::<T, R>(
+                                &mut root,
+                                order,
+                                settings.max_bin,
+                                settings,
+                                &mut packings,
+                            );
+
+                            (result, packings)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+fn all_inserted<T: Coord, R: OutputRect<T>>(
+    ordering: &[Subject<T>],
+    root: &mut impl Packer<T>,
+    total_inserted_area: &mut TotalAreaType<T>,
 ) -> bool {
     for r in ordering {
-        let rect = unsafe { r.read() };
-        if root.insert((&rect).into()).is_some() {
-            *total_inserted_area += rect.area();
+        if root.insert_padded::<R>(r.wh, r.padding).is_ok() {
+            *total_inserted_area += r.wh.area();
         } else {
             return false;
         }
@@ -202,49 +820,75 @@ fn all_inserted(
     true
 }
 
-fn try_pack(
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    ordering: &[*mut RectXYWH],
-    starting_bin: RectWH,
-    discard_step: i32,
+fn try_pack<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    starting_bin: RectWH<T>,
+    settings: SearchSettings<'_, T>,
     tried_dimension: BinDimension,
-) -> BestPackingReturn {
-    best_packing_for_ordering_impl(root, ordering, starting_bin, discard_step, tried_dimension)
+    packings: &mut usize,
+) -> BestPackingReturn<T> {
+    best_packing_for_ordering_impl::<T, R>(
+        root,
+        ordering,
+        starting_bin,
+        settings,
+        tried_dimension,
+        packings,
+    )
 }
 
-fn trial(
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    ordering: &[*mut RectXYWH],
-    best_bin: &mut RectWH,
-    discard_step: i32,
+fn trial<T: Coord, R: OutputRect<T>>(
+    root: &mut impl Packer<T>,
+    ordering: &[Subject<T>],
+    best_bin: &mut RectWH<T>,
+    settings: SearchSettings<'_, T>,
     tried_dimension: BinDimension,
+    packings: &mut usize,
 ) {
-    if let BestPackingReturn::Rect(better) =
-        try_pack(root, ordering, *best_bin, discard_step, tried_dimension)
-    {
+    if let BestPackingReturn::Rect(better) = try_pack::<T, R>(
+        root,
+        ordering,
+        *best_bin,
+        settings,
+        tried_dimension,
+        packings,
+    ) {
         *best_bin = better;
     }
 }
 
-fn for_each_order_lambda<'a>(
-    root: &mut EmptySpaces<impl EmptySpacesProviderTrait>,
-    current_order: &'a [*mut RectXYWH],
-    max_bin: RectWH,
-    discard_step: i32,
-    best_order: &mut Option<&'a [*mut RectXYWH]>,
-    best_total_inserted: &mut i32,
-    best_bin: &mut RectWH,
+/// Compares the search result of the trial at `order_index` with the best one so far.
+/// A trial that fit everything always beats one that didn't.
+fn for_each_order_lambda<T: Coord>(
+    order_index: usize,
+    result: BestPackingReturn<T>,
+    best_order: &mut Option<usize>,
+    best_total_inserted: &mut Option<TotalAreaType<T>>,
+    best_value: &mut Option<f64>,
+    best_bin: &mut RectWH<T>,
 ) {
-    match best_packing_for_ordering(root, current_order, max_bin, discard_step) {
+    match result {
         BestPackingReturn::TotalArea(total_inserted) => {
-            if best_order.is_none() && total_inserted > *best_total_inserted {
-                *best_order = Some(current_order);
-                *best_total_inserted = total_inserted;
+            if best_order.is_none() && best_total_inserted.is_none_or(|b| total_inserted > b) {
+                *best_order = Some(order_index);
+                *best_total_inserted = Some(total_inserted);
+            }
+        }
+        BestPackingReturn::Value(value) => {
+            // Only trials that didn't fit everything have a value, so if one is set,
+            // no trial has fit everything yet.
+            let fit_everything = best_order.is_some() && best_value.is_none();
+
+            if !fit_everything && best_value.is_none_or(|b| value > b) {
+                *best_order = Some(order_index);
+                *best_value = Some(value);
             }
         }
         BestPackingReturn::Rect(result_bin) => {
             if result_bin.area() <= best_bin.area() {
-                *best_order = Some(current_order);
+                *best_order = Some(order_index);
+                *best_value = None;
                 *best_bin = result_bin;
             }
         }
@@ -0,0 +1,681 @@
+//! Packs named rectangles read from JSON or CSV, and writes the result as JSON.
+//! Run with `--help` for the details.
+
+use std::{
+    cmp::Ordering,
+    env, fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use rectpack2d_rs::{
+    best_bin_finder::{BinSizePolicy, CallbackResult, SearchMode},
+    empty_space_allocators::DefaultEmptySpaces,
+    empty_spaces::{EmptySpaces, Padding},
+    finders_interface::{
+        Input, MultiBinPacking, PackingResult, find_best_packing_multi_ordered, pack_ordered,
+    },
+    max_rects::MaxRects,
+    packer::Packer,
+    rect_structs::{OutputRect, RectWH, RectXYWH, RectXYWHF},
+    skyline::Skyline,
+};
+use serde::{Deserialize, Serialize};
+
+const USAGE: &str = "\
+Usage: rectpack2d [OPTIONS] [FILE]
+
+Packs the rectangles in FILE, or in the standard input if FILE is missing or `-`,
+and writes the placements and the bin size to the standard output as JSON.
+With --multi, whatever doesn't fit into one bin goes into the next one,
+and every placement says which of the `pages` it's on.
+
+Input formats:
+  JSON  [{\"name\": \"hero\", \"w\": 32, \"h\": 48}, ...]
+  CSV   one `name,w,h` line per rectangle, with an optional header; names may be
+        in double quotes, and lines starting with `#` are skipped
+
+Options:
+  --format <json|csv>          Input format [default: from the file extension, then the contents]
+  --max-bin <SIDE|WxH>         Largest bin to try [default: 4096]
+  --discard-step <N>           See `Input::discard_step` [default: 1]
+  --orderings <LIST>           Comma-separated orderings to try, out of
+                               area, perimeter, max-side, width, height [default: all]
+  --mode <MODE>                both, fixed-width or fixed-height [default: both]
+  --packer <PACKER>            empty-spaces, max-rects or skyline [default: empty-spaces]
+  --bin-size-policy <POLICY>   free, pow2, square-pow2 or multiple-of:N [default: free]
+  --spacing <N>                Gap between rectangles [default: 0]
+  --edge-margin <N>            Gap between the rectangles and the bin border [default: 0]
+  --allow-flip                 Allow rotating rectangles by 90 degrees
+  --parallel                   Search on multiple threads
+  --multi                      Pack into as many bins as necessary
+  -h, --help                   Print this message
+
+Exit codes:
+  0  every rectangle was placed
+  1  some rectangles didn't fit
+  2  the arguments or the input are invalid";
+
+#[derive(Deserialize)]
+struct NamedRect {
+    name: String,
+    w: i32,
+    h: i32,
+}
+
+#[derive(Serialize)]
+struct Output {
+    /// Only for a single bin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bin: Option<Size>,
+    /// Only with `--multi`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pages: Option<Vec<Page>>,
+    all_placed: bool,
+    /// Only for a single bin, since every page has its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    occupancy: Option<f64>,
+    rects: Vec<Placement>,
+}
+
+#[derive(Serialize)]
+struct Size {
+    w: i32,
+    h: i32,
+}
+
+#[derive(Serialize)]
+struct Page {
+    w: i32,
+    h: i32,
+    occupancy: f64,
+}
+
+#[derive(Serialize)]
+struct Placement {
+    name: String,
+    placed: bool,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    flipped: bool,
+    /// The index into `pages`, only with `--multi`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy)]
+enum PackerKind {
+    EmptySpaces,
+    MaxRects,
+    Skyline,
+}
+
+struct Args {
+    file: Option<String>,
+    format: Option<Format>,
+    max_bin: RectWH,
+    discard_step: i32,
+    /// Indices into `ORDERINGS`.
+    orderings: Vec<usize>,
+    mode: SearchMode,
+    packer: PackerKind,
+    bin_size_policy: BinSizePolicy,
+    padding: Padding,
+    allow_flip: bool,
+    parallel: bool,
+    multi: bool,
+}
+
+type Orderer = fn(&RectWH, &RectWH) -> Ordering;
+
+const ORDERINGS: [(&str, Orderer); 5] = [
+    ("area", |l, r| l.area().cmp(&r.area())),
+    ("perimeter", |l, r| l.perimeter().cmp(&r.perimeter())),
+    ("max-side", |l, r| l.max_side().cmp(&r.max_side())),
+    ("width", |l, r| l.w.cmp(&r.w)),
+    ("height", |l, r| l.h.cmp(&r.h)),
+];
+
+fn main() -> ExitCode {
+    ExitCode::from(exit_code(run()))
+}
+
+/// See the exit codes in `USAGE`.
+fn exit_code(result: Result<bool, String>) -> u8 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(message) => {
+            eprintln!("rectpack2d: {message}");
+            2
+        }
+    }
+}
+
+/// Returns whether every rectangle was placed.
+fn run() -> Result<bool, String> {
+    let Some(args) = parse_args(env::args().skip(1))? else {
+        println!("{USAGE}");
+        return Ok(true);
+    };
+
+    let text = match args.file.as_deref() {
+        None | Some("-") => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("can't read the standard input: {e}"))?;
+            text
+        }
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?,
+    };
+
+    let output = pack_text(&text, &args)?;
+
+    let json = serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?;
+    println!("{json}");
+
+    Ok(output.all_placed)
+}
+
+/// Reads the rectangles in `text` and packs them.
+fn pack_text(text: &str, args: &Args) -> Result<Output, String> {
+    let format = args
+        .format
+        .unwrap_or_else(|| guess_format(&args.file, text));
+    let named = match format {
+        Format::Json => serde_json::from_str::<Vec<NamedRect>>(text).map_err(|e| e.to_string())?,
+        Format::Csv => parse_csv(text)?,
+    };
+
+    match args.packer {
+        PackerKind::EmptySpaces => {
+            pack_with(EmptySpaces::<DefaultEmptySpaces>::default(), &named, args)
+        }
+        PackerKind::MaxRects => pack_with(MaxRects::default(), &named, args),
+        PackerKind::Skyline => pack_with(Skyline::default(), &named, args),
+    }
+}
+
+fn pack_with<P: Packer>(mut root: P, named: &[NamedRect], args: &Args) -> Result<Output, String> {
+    if args.allow_flip {
+        pack_as::<P, RectXYWHF>(&mut root, named, args)
+    } else {
+        pack_as::<P, RectXYWH>(&mut root, named, args)
+    }
+}
+
+fn pack_as<P: Packer, R: OutputRect>(
+    root: &mut P,
+    named: &[NamedRect],
+    args: &Args,
+) -> Result<Output, String> {
+    let sizes = named
+        .iter()
+        .map(|r| RectWH::new(r.w, r.h))
+        .collect::<Vec<_>>();
+
+    let input = Input::new(
+        0,
+        args.discard_step,
+        |_| CallbackResult::ContinuePacking,
+        |_| CallbackResult::ContinuePacking,
+    )
+    .with_max_bin(args.max_bin)
+    .with_padding(args.padding)
+    .with_bin_size_policy(args.bin_size_policy)
+    .with_search_mode(args.mode)
+    .with_parallel(args.parallel);
+
+    if args.multi {
+        return pack_multi_as(root, named, &input, &args.orderings);
+    }
+
+    // `pack_ordered` takes an array, so every possible number of orderings gets its own call.
+    let orderings = args
+        .orderings
+        .iter()
+        .map(|&i| ORDERINGS[i].1)
+        .collect::<Vec<_>>();
+    let result: PackingResult<R> = match orderings.len() {
+        1 => pack_ordered(root, &sizes, &input, to_array::<_, 1>(&orderings)),
+        2 => pack_ordered(root, &sizes, &input, to_array::<_, 2>(&orderings)),
+        3 => pack_ordered(root, &sizes, &input, to_array::<_, 3>(&orderings)),
+        4 => pack_ordered(root, &sizes, &input, to_array::<_, 4>(&orderings)),
+        _ => pack_ordered(root, &sizes, &input, to_array::<_, 5>(&orderings)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(Output {
+        bin: Some(Size {
+            w: result.bin.w,
+            h: result.bin.h,
+        }),
+        pages: None,
+        // Zero-area rectangles are reported as not placed, but don't count as failures.
+        all_placed: result.all_placed(),
+        occupancy: Some(result.occupancy),
+        rects: placements(named, &result.placements, None),
+    })
+}
+
+fn pack_multi_as<P: Packer, R: OutputRect, F, G>(
+    root: &mut P,
+    named: &[NamedRect],
+    input: &Input<F, G>,
+    orderings: &[usize],
+) -> Result<Output, String>
+where
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+{
+    let mut rects = named
+        .iter()
+        .map(|r| R::placed(0, 0, r.w, r.h, false))
+        .collect::<Vec<_>>();
+
+    let table = output_orderings::<R>();
+    let orderings = orderings.iter().map(|&i| table[i]).collect::<Vec<_>>();
+    let subjects = rects.iter_mut();
+
+    let packing: MultiBinPacking = match orderings.len() {
+        1 => find_best_packing_multi_ordered(root, subjects, input, to_array::<_, 1>(&orderings)),
+        2 => find_best_packing_multi_ordered(root, subjects, input, to_array::<_, 2>(&orderings)),
+        3 => find_best_packing_multi_ordered(root, subjects, input, to_array::<_, 3>(&orderings)),
+        4 => find_best_packing_multi_ordered(root, subjects, input, to_array::<_, 4>(&orderings)),
+        _ => find_best_packing_multi_ordered(root, subjects, input, to_array::<_, 5>(&orderings)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let placed = rects
+        .iter()
+        .zip(&packing.pages)
+        .map(|(rect, page)| page.map(|_| *rect))
+        .collect::<Vec<_>>();
+
+    Ok(Output {
+        bin: None,
+        pages: Some(
+            packing
+                .bins
+                .iter()
+                .zip(&packing.stats)
+                .map(|(bin, stats)| Page {
+                    w: bin.w,
+                    h: bin.h,
+                    occupancy: stats.occupancy,
+                })
+                .collect(),
+        ),
+        all_placed: packing.all_placed(),
+        occupancy: None,
+        rects: placements(named, &placed, Some(&packing.pages)),
+    })
+}
+
+/// The output of every rectangle, in the order they were read in.
+fn placements<R: OutputRect>(
+    named: &[NamedRect],
+    placements: &[Option<R>],
+    pages: Option<&[Option<usize>]>,
+) -> Vec<Placement> {
+    named
+        .iter()
+        .zip(placements)
+        .enumerate()
+        .map(|(i, (rect, placement))| {
+            let placed = placement.map(|p| (p.get_xywh(), p.flipped()));
+            let (xywh, flipped) = placed.unwrap_or((RectXYWH::from_wh(rect.w, rect.h), false));
+
+            Placement {
+                name: rect.name.clone(),
+                placed: placed.is_some(),
+                x: xywh.x,
+                y: xywh.y,
+                w: xywh.w,
+                h: xywh.h,
+                flipped,
+                page: pages.and_then(|pages| pages[i]),
+            }
+        })
+        .collect()
+}
+
+fn to_array<T: Copy, const N: usize>(items: &[T]) -> [T; N] {
+    std::array::from_fn(|i| items[i])
+}
+
+/// `ORDERINGS` for the rectangles that `find_best_packing_multi_ordered` takes.
+fn output_orderings<R: OutputRect>() -> [fn(R, R) -> Ordering; 5] {
+    fn by<R: OutputRect, const I: usize>(l: R, r: R) -> Ordering {
+        ORDERINGS[I].1(&l.get_wh(), &r.get_wh())
+    }
+
+    [by::<R, 0>, by::<R, 1>, by::<R, 2>, by::<R, 3>, by::<R, 4>]
+}
+
+fn guess_format(file: &Option<String>, text: &str) -> Format {
+    match file.as_deref().and_then(|f| f.rsplit_once('.')) {
+        Some((_, ext)) if ext.eq_ignore_ascii_case("csv") => Format::Csv,
+        Some((_, ext)) if ext.eq_ignore_ascii_case("json") => Format::Json,
+        _ if text.trim_start().starts_with('[') => Format::Json,
+        _ => Format::Csv,
+    }
+}
+
+/// Parses `name,w,h` lines, skipping blank lines and `#` comments. A first line whose
+/// sizes aren't numbers is taken as the header.
+fn parse_csv(text: &str) -> Result<Vec<NamedRect>, String> {
+    let mut rects = Vec::new();
+    let mut first = true;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = split_csv_line(line)
+            .ok_or_else(|| format!("line {}: a quoted field is malformed", i + 1))?;
+
+        let [name, w, h] = &fields[..] else {
+            return Err(format!("line {}: expected `name,w,h`", i + 1));
+        };
+
+        match (w.parse(), h.parse()) {
+            (Ok(w), Ok(h)) => rects.push(NamedRect {
+                name: name.clone(),
+                w,
+                h,
+            }),
+            _ if first => {}
+            _ => return Err(format!("line {}: the sizes must be integers", i + 1)),
+        }
+
+        first = false;
+    }
+
+    Ok(rects)
+}
+
+/// Splits a CSV line into its trimmed fields. Fields in double quotes may contain
+/// commas, and `""` stands for a quote in them. Returns `None` if a quote isn't
+/// closed, or is followed by anything but the next field.
+fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+
+    loop {
+        let field = rest.trim_start();
+
+        let Some(quoted) = field.strip_prefix('"') else {
+            match field.split_once(',') {
+                Some((value, next)) => {
+                    fields.push(value.trim().to_owned());
+                    rest = next;
+                    continue;
+                }
+                None => {
+                    fields.push(field.trim().to_owned());
+                    return Some(fields);
+                }
+            }
+        };
+
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+
+        let after = loop {
+            match chars.next()? {
+                (i, '"') if quoted[i + 1..].starts_with('"') => {
+                    value.push('"');
+                    chars.next();
+                }
+                (i, '"') => break quoted[i + 1..].trim_start(),
+                (_, c) => value.push(c),
+            }
+        };
+
+        fields.push(value);
+
+        if after.is_empty() {
+            return Some(fields);
+        }
+
+        rest = after.strip_prefix(',')?;
+    }
+}
+
+/// Returns `None` if the usage should be printed.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        file: None,
+        format: None,
+        max_bin: RectWH::new(4096, 4096),
+        discard_step: 1,
+        orderings: (0..ORDERINGS.len()).collect(),
+        mode: SearchMode::Both,
+        packer: PackerKind::EmptySpaces,
+        bin_size_policy: BinSizePolicy::Free,
+        padding: Padding::default(),
+        allow_flip: false,
+        parallel: false,
+        multi: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--format" => {
+                parsed.format = Some(match value()?.as_str() {
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("unknown format `{other}`")),
+                })
+            }
+            "--max-bin" => parsed.max_bin = parse_bin(&value()?)?,
+            "--discard-step" => parsed.discard_step = parse_number(&value()?)?,
+            "--orderings" => {
+                parsed.orderings = value()?
+                    .split(',')
+                    .map(|name| {
+                        ORDERINGS
+                            .iter()
+                            .position(|&(n, _)| n == name.trim())
+                            .ok_or_else(|| format!("unknown ordering `{name}`"))
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                if !(1..=ORDERINGS.len()).contains(&parsed.orderings.len()) {
+                    return Err(format!("expected 1 to {} orderings", ORDERINGS.len()));
+                }
+            }
+            "--mode" => {
+                parsed.mode = match value()?.as_str() {
+                    "both" => SearchMode::Both,
+                    "fixed-width" => SearchMode::FixedWidth,
+                    "fixed-height" => SearchMode::FixedHeight,
+                    other => return Err(format!("unknown mode `{other}`")),
+                }
+            }
+            "--packer" => {
+                parsed.packer = match value()?.as_str() {
+                    "empty-spaces" => PackerKind::EmptySpaces,
+                    "max-rects" => PackerKind::MaxRects,
+                    "skyline" => PackerKind::Skyline,
+                    other => return Err(format!("unknown packer `{other}`")),
+                }
+            }
+            "--bin-size-policy" => {
+                let policy = value()?;
+
+                parsed.bin_size_policy = match policy.split_once(':') {
+                    None if policy == "free" => BinSizePolicy::Free,
+                    None if policy == "pow2" => BinSizePolicy::PowerOfTwo,
+                    None if policy == "square-pow2" => BinSizePolicy::SquarePowerOfTwo,
+                    Some(("multiple-of", n)) => BinSizePolicy::MultipleOf(parse_number(n)?),
+                    _ => return Err(format!("unknown bin size policy `{policy}`")),
+                }
+            }
+            "--spacing" => parsed.padding.spacing = parse_number(&value()?)?,
+            "--edge-margin" => parsed.padding.edge_margin = parse_number(&value()?)?,
+            "--allow-flip" => parsed.allow_flip = true,
+            "--parallel" => parsed.parallel = true,
+            "--multi" => parsed.multi = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{arg}`")),
+            _ if parsed.file.is_none() => parsed.file = Some(arg),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+/// Parses either a single side, or `WxH`.
+fn parse_bin(value: &str) -> Result<RectWH, String> {
+    match value.split_once(['x', 'X']) {
+        Some((w, h)) => Ok(RectWH::new(parse_number(w)?, parse_number(h)?)),
+        None => {
+            let side = parse_number(value)?;
+            Ok(RectWH::new(side, side))
+        }
+    }
+}
+
+fn parse_number(value: &str) -> Result<i32, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{value}` isn't an integer"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(list.iter().map(|arg| arg.to_string()))
+    }
+
+    fn exit_code_of(text: &str, list: &[&str]) -> u8 {
+        let output = args(list).and_then(|args| pack_text(text, &args.unwrap()));
+        exit_code(output.map(|output| output.all_placed))
+    }
+
+    #[test]
+    fn csv() {
+        let rects = parse_csv("name, w, h\nhero,32,48\n\n  tile , 16, 16\n").unwrap();
+        let rects = rects
+            .iter()
+            .map(|r| (r.name.as_str(), r.w, r.h))
+            .collect::<Vec<_>>();
+        assert_eq!(rects, [("hero", 32, 48), ("tile", 16, 16)]);
+
+        // Without a header, the first line is a rectangle as well.
+        assert_eq!(parse_csv("hero,32,48\ntile,16,16").unwrap().len(), 2);
+
+        assert_eq!(
+            parse_csv("hero,32,48\ntile,16").err().unwrap(),
+            "line 2: expected `name,w,h`"
+        );
+        assert_eq!(
+            parse_csv("hero,32,48\ntile,16,big").err().unwrap(),
+            "line 2: the sizes must be integers"
+        );
+
+        // The header may come after blank lines and comments.
+        let rects = parse_csv("# sprites\n\n# name, size\nname,w,h\nhero,32,48").unwrap();
+        assert_eq!(rects.len(), 1);
+        assert_eq!(
+            parse_csv("# sprites\nname,w,h\ntile,16,w").err().unwrap(),
+            "line 3: the sizes must be integers"
+        );
+
+        // Quoted names may contain commas and quotes.
+        let rects = parse_csv("\"hero, idle\",32,48\n \"say \"\"hi\"\"\" , 8,8").unwrap();
+        let names = rects.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["hero, idle", "say \"hi\""]);
+
+        assert_eq!(
+            parse_csv("\"hero,32,48").err().unwrap(),
+            "line 1: a quoted field is malformed"
+        );
+        assert_eq!(
+            parse_csv("\"hero\"x,32,48").err().unwrap(),
+            "line 1: a quoted field is malformed"
+        );
+    }
+
+    #[test]
+    fn format() {
+        let file = |name: &str| Some(name.to_owned());
+
+        assert!(matches!(guess_format(&file("a.CSV"), "[]"), Format::Csv));
+        assert!(matches!(guess_format(&file("a.json"), ""), Format::Json));
+        assert!(matches!(guess_format(&None, "  [{}]"), Format::Json));
+        assert!(matches!(guess_format(&file("a.txt"), "a,1,2"), Format::Csv));
+    }
+
+    #[test]
+    fn arguments() {
+        let parsed = args(&["--max-bin", "300x200", "rects.csv"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.max_bin, RectWH::new(300, 200));
+        assert_eq!(parsed.file.as_deref(), Some("rects.csv"));
+
+        let parsed = args(&["--max-bin", "512", "--orderings", "height, area"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.max_bin, RectWH::new(512, 512));
+        assert_eq!(parsed.orderings, [4, 0]);
+
+        assert!(args(&["--help"]).unwrap().is_none());
+        assert_eq!(
+            args(&["--max-bin", "3x"]).err().unwrap(),
+            "`` isn't an integer"
+        );
+        assert_eq!(
+            args(&["--max-bin"]).err().unwrap(),
+            "--max-bin needs a value"
+        );
+        assert_eq!(
+            args(&["--frobnicate"]).err().unwrap(),
+            "unknown option `--frobnicate`"
+        );
+        assert_eq!(exit_code_of("", &["--frobnicate"]), 2);
+    }
+
+    #[test]
+    fn exit_codes() {
+        let text = "hero,60,10\ntile,10,10\n";
+
+        assert_eq!(exit_code_of(text, &["--max-bin", "100"]), 0);
+        assert_eq!(exit_code_of(text, &["--max-bin", "50"]), 1);
+        assert_eq!(exit_code_of("hero,60", &[]), 2);
+    }
+
+    #[test]
+    fn multi() {
+        let args = args(&["--multi", "--max-bin", "64"]).unwrap().unwrap();
+        let output = pack_text("a,64,64\nb,64,64\nc,32,32\nd,100,1\n", &args).unwrap();
+
+        assert!(output.bin.is_none());
+        assert_eq!(output.pages.as_ref().map(Vec::len), Some(3));
+        assert!(!output.all_placed);
+
+        let pages = output.rects.iter().map(|r| r.page).collect::<Vec<_>>();
+        assert_eq!(pages, [Some(0), Some(1), Some(2), None]);
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
+};
+
+/// Types that can be used for the positions and sizes of rectangles.
+pub trait Coord:
+    Copy
+    + Default
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Send
+    + Sync
+    + 'static
+{
+    /// A wider type that areas are computed and summed up in, so that they don't overflow.
+    type Area: Copy
+        + Default
+        + Debug
+        + PartialEq
+        + PartialOrd
+        + Add<Output = Self::Area>
+        + Sub<Output = Self::Area>
+        + Mul<Output = Self::Area>
+        + AddAssign
+        + SubAssign
+        + Send
+        + Sync
+        + 'static;
+
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+
+    fn to_area(self) -> Self::Area;
+
+    /// Converts an area for computing ratios, such as occupancy.
+    fn area_to_f64(area: Self::Area) -> f64;
+
+    /// Converts a step count, such as `Input::discard_step`, saturating if it doesn't fit.
+    fn from_i32(v: i32) -> Self;
+
+    /// The smallest power of two not less than `self`,
+    /// or the largest one not greater than it if that would overflow.
+    fn round_up_pow2(self) -> Self;
+
+    /// The largest power of two not greater than `self`.
+    fn round_down_pow2(self) -> Self;
+
+    /// The smallest multiple of `n` not less than `self`,
+    /// or the largest one not greater than it if that would overflow.
+    fn round_up_to_multiple(self, n: Self) -> Self;
+
+    /// The largest multiple of `n` not greater than `self`.
+    fn round_down_to_multiple(self, n: Self) -> Self;
+}
+
+pub(crate) fn max<T: Coord>(a: T, b: T) -> T {
+    if a < b { b } else { a }
+}
+
+pub(crate) fn min<T: Coord>(a: T, b: T) -> T {
+    if b < a { b } else { a }
+}
+
+/// Whether `v` is greater than zero, which NaN isn't.
+pub(crate) fn is_positive<T: Coord>(v: T) -> bool {
+    v > T::ZERO
+}
+
+/// Whether `v` is zero or greater, which NaN isn't.
+pub(crate) fn is_non_negative<T: Coord>(v: T) -> bool {
+    v >= T::ZERO
+}
+
+/// `a - b`, or zero if that would be negative.
+pub(crate) fn sub_or_zero<T: Coord>(a: T, b: T) -> T {
+    if a > b { a - b } else { T::ZERO }
+}
+
+macro_rules! impl_coord_int {
+    ($t:ty, $area:ty, $unsigned:ty) => {
+        impl Coord for $t {
+            type Area = $area;
+
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const TWO: Self = 2;
+
+            fn to_area(self) -> Self::Area {
+                self as $area
+            }
+
+            fn area_to_f64(area: Self::Area) -> f64 {
+                area as f64
+            }
+
+            fn from_i32(v: i32) -> Self {
+                (v as i128).clamp(<$t>::MIN as i128, <$t>::MAX as i128) as $t
+            }
+
+            fn round_up_pow2(self) -> Self {
+                if self < 1 {
+                    return 0;
+                }
+
+                match (self as $unsigned).checked_next_power_of_two() {
+                    Some(p) if p <= <$t>::MAX as $unsigned => p as $t,
+                    _ => self.round_down_pow2(),
+                }
+            }
+
+            fn round_down_pow2(self) -> Self {
+                if self < 1 { 0 } else { 1 << self.ilog2() }
+            }
+
+            fn round_up_to_multiple(self, n: Self) -> Self {
+                if n <= 1 {
+                    return self;
+                }
+
+                match self.checked_add(n - 1) {
+                    Some(v) => v / n * n,
+                    None => self.round_down_to_multiple(n),
+                }
+            }
+
+            fn round_down_to_multiple(self, n: Self) -> Self {
+                if n <= 1 { self } else { self / n * n }
+            }
+        }
+    };
+}
+
+macro_rules! impl_coord_float {
+    ($t:ty, $area:ty) => {
+        impl Coord for $t {
+            type Area = $area;
+
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+            const TWO: Self = 2.0;
+
+            fn to_area(self) -> Self::Area {
+                self as $area
+            }
+
+            fn area_to_f64(area: Self::Area) -> f64 {
+                area as f64
+            }
+
+            fn from_i32(v: i32) -> Self {
+                v as $t
+            }
+
+            fn round_up_pow2(self) -> Self {
+                if self <= 0.0 {
+                    0.0
+                } else {
+                    self.log2().ceil().exp2()
+                }
+            }
+
+            fn round_down_pow2(self) -> Self {
+                if self <= 0.0 {
+                    0.0
+                } else {
+                    self.log2().floor().exp2()
+                }
+            }
+
+            fn round_up_to_multiple(self, n: Self) -> Self {
+                if n <= 0.0 {
+                    self
+                } else {
+                    (self / n).ceil() * n
+                }
+            }
+
+            fn round_down_to_multiple(self, n: Self) -> Self {
+                if n <= 0.0 {
+                    self
+                } else {
+                    (self / n).floor() * n
+                }
+            }
+        }
+    };
+}
+
+impl_coord_int!(u16, u64, u16);
+impl_coord_int!(u32, u64, u32);
+impl_coord_int!(u64, u128, u64);
+impl_coord_int!(i16, i64, u16);
+impl_coord_int!(i32, i64, u32);
+impl_coord_int!(i64, i128, u64);
+impl_coord_float!(f32, f64);
+impl_coord_float!(f64, f64);
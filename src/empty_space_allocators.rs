@@ -1,16 +1,16 @@
-use crate::{empty_spaces::EmptySpacesProviderTrait, rect_structs::RectXYWH};
+use crate::{coord::Coord, empty_spaces::EmptySpacesProviderTrait, rect_structs::RectXYWH};
 
 #[derive(Default)]
-pub struct DefaultEmptySpaces {
-    empty_spaces: Vec<RectXYWH>,
+pub struct DefaultEmptySpaces<T: Coord = i32> {
+    empty_spaces: Vec<RectXYWH<T>>,
 }
 
-impl EmptySpacesProviderTrait for DefaultEmptySpaces {
+impl<T: Coord> EmptySpacesProviderTrait<T> for DefaultEmptySpaces<T> {
     fn reset(&mut self) {
         self.empty_spaces.clear();
     }
 
-    fn get(&self, i: usize) -> RectXYWH {
+    fn get(&self, i: usize) -> RectXYWH<T> {
         self.empty_spaces[i]
     }
 
@@ -23,18 +23,18 @@ impl EmptySpacesProviderTrait for DefaultEmptySpaces {
         self.empty_spaces.pop();
     }
 
-    fn add(&mut self, rect: RectXYWH) -> bool {
+    fn add(&mut self, rect: RectXYWH<T>) -> bool {
         self.empty_spaces.push(rect);
         true
     }
 }
 
-pub struct StaticEmptySpaces<const MAX_SPACES: usize> {
+pub struct StaticEmptySpaces<const MAX_SPACES: usize, T: Coord = i32> {
     count_spaces: usize,
-    empty_spaces: [RectXYWH; MAX_SPACES],
+    empty_spaces: [RectXYWH<T>; MAX_SPACES],
 }
 
-impl<const MAX_SPACES: usize> Default for StaticEmptySpaces<MAX_SPACES> {
+impl<const MAX_SPACES: usize, T: Coord> Default for StaticEmptySpaces<MAX_SPACES, T> {
     fn default() -> Self {
         Self {
             count_spaces: 0,
@@ -43,12 +43,14 @@ impl<const MAX_SPACES: usize> Default for StaticEmptySpaces<MAX_SPACES> {
     }
 }
 
-impl<const MAX_SPACES: usize> EmptySpacesProviderTrait for StaticEmptySpaces<MAX_SPACES> {
+impl<const MAX_SPACES: usize, T: Coord> EmptySpacesProviderTrait<T>
+    for StaticEmptySpaces<MAX_SPACES, T>
+{
     fn reset(&mut self) {
         self.count_spaces = 0
     }
 
-    fn get(&self, i: usize) -> RectXYWH {
+    fn get(&self, i: usize) -> RectXYWH<T> {
         self.empty_spaces[i]
     }
 
@@ -61,7 +63,7 @@ impl<const MAX_SPACES: usize> EmptySpacesProviderTrait for StaticEmptySpaces<MAX
         self.count_spaces -= 1;
     }
 
-    fn add(&mut self, r: RectXYWH) -> bool {
+    fn add(&mut self, r: RectXYWH<T>) -> bool {
         if self.count_spaces >= self.empty_spaces.len() {
             return false;
         }
@@ -71,4 +73,8 @@ impl<const MAX_SPACES: usize> EmptySpacesProviderTrait for StaticEmptySpaces<MAX
 
         true
     }
+
+    fn remaining_capacity(&self) -> Option<usize> {
+        Some(MAX_SPACES - self.count_spaces)
+    }
 }
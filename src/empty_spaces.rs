@@ -1,26 +1,91 @@
 use crate::{
-    insert_and_split::{CreatedSplits, insert_and_split},
-    rect_structs::{RectWH, RectXYWH},
+    coord::{self, Coord},
+    error::PackError,
+    insert_and_split::{CreatedSplits, SplitRule, cut_out, insert_and_split},
+    packer::{PaddedRect, expand_with_reserved, free_space},
+    rect_structs::{OutputRect, RectWH, RectXYWH, TotalAreaType},
 };
 
-pub trait EmptySpacesProviderTrait: Default {
+pub trait EmptySpacesProviderTrait<T: Coord = i32>: Default {
     fn reset(&mut self);
-    fn get(&self, i: usize) -> RectXYWH;
+    fn get(&self, i: usize) -> RectXYWH<T>;
     fn get_count(&self) -> usize;
     fn remove(&mut self, i: usize);
-    fn add(&mut self, rect: RectXYWH) -> bool;
+    fn add(&mut self, rect: RectXYWH<T>) -> bool;
+
+    /// How many more spaces `add` can take, or `None` if there's no limit.
+    fn remaining_capacity(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Gaps to keep free around the inserted rectangles.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Padding<T: Coord = i32> {
+    /// Gap between two neighbouring rectangles.
+    pub spacing: T,
+    /// Gap between the rectangles and the border of the bin.
+    pub edge_margin: T,
+}
+
+impl<T: Coord> Padding<T> {
+    pub fn new(spacing: T, edge_margin: T) -> Self {
+        Self {
+            spacing,
+            edge_margin,
+        }
+    }
+}
+
+/// How `EmptySpaces::insert` picks the empty space a rectangle goes into.
+/// Every strategy other than `ReverseFirstFit` looks at all of the spaces,
+/// and picks the one with the lowest score.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SpaceSelection {
+    /// Takes the first space that fits, going from the most recently created one.
+    /// This is what rectpack2D does.
+    #[default]
+    ReverseFirstFit,
+    /// Minimizes the area left over in the space.
+    BestAreaFit,
+    /// Minimizes the shorter side left over in the space.
+    BestShortSideFit,
+    /// Minimizes the longer side left over in the space.
+    BestLongSideFit,
+    /// Places rectangles as high up, then as far left as possible.
+    BottomLeft,
+}
+
+/// The configuration of an `EmptySpaces`, see [`Packer::Settings`](crate::packer::Packer::Settings).
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct EmptySpacesSettings<T: Coord = i32> {
+    pub padding: Padding<T>,
+    pub split_rule: SplitRule,
+    pub selection: SpaceSelection,
 }
 
+/// The index of a space, the space itself, how it's split by a rectangle, and whether
+/// the rectangle is flipped.
+type Choice<T> = (usize, RectXYWH<T>, CreatedSplits<T>, bool);
+
 #[derive(Default)]
-pub struct EmptySpaces<EmptySpacesProvider: EmptySpacesProviderTrait> {
-    current_aabb: RectWH,
+pub struct EmptySpaces<EmptySpacesProvider: EmptySpacesProviderTrait<T>, T: Coord = i32> {
+    current_aabb: RectWH<T>,
+    /// The bin given to the last `reset`.
+    bin: RectWH<T>,
+    settings: EmptySpacesSettings<T>,
     spaces: EmptySpacesProvider,
 }
 
-impl<EmptySpacesProvider: EmptySpacesProviderTrait> EmptySpaces<EmptySpacesProvider> {
-    pub fn new(r: RectWH) -> Self {
+impl<EmptySpacesProvider: EmptySpacesProviderTrait<T>, T: Coord>
+    EmptySpaces<EmptySpacesProvider, T>
+{
+    pub fn new(r: RectWH<T>) -> Self {
         let mut ret = Self {
             current_aabb: RectWH::default(),
+            bin: RectWH::default(),
+            settings: EmptySpacesSettings::default(),
             spaces: EmptySpacesProvider::default(),
         };
 
@@ -29,61 +94,283 @@ impl<EmptySpacesProvider: EmptySpacesProviderTrait> EmptySpaces<EmptySpacesProvi
         ret
     }
 
-    pub fn insert(&mut self, image_rectangle: RectWH) -> Option<RectXYWH> {
+    /// Inserts a rectangle into the first empty space that fits it, or the best one
+    /// if another `SpaceSelection` is set. If `R` allows flipping, the rotated orientation
+    /// is tried as well, and is preferred whenever it leaves fewer empty spaces behind.
+    pub fn insert<R: OutputRect<T>>(&mut self, image_rectangle: RectWH<T>) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, T::ZERO)
+    }
+
+    /// Like `insert`, but also keeps `padding` free on every side of the rectangle,
+    /// on top of the spacing set with `set_padding`. The returned rectangle
+    /// doesn't include any of the padding.
+    pub fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        let padded = PaddedRect::new(image_rectangle, padding, self.settings.padding);
+
+        let (i, candidate_space, splits, flipped) =
+            if self.settings.selection == SpaceSelection::ReverseFirstFit {
+                self.first_fit::<R>(padded.size())
+            } else {
+                self.best_fit::<R>(padded.size())
+            }
+            .ok_or(PackError::DidNotFit)?;
+
+        self.accept_result(i, &splits)?;
+
+        Ok(padded.place(
+            candidate_space.x,
+            candidate_space.y,
+            flipped,
+            &mut self.current_aabb,
+        ))
+    }
+
+    /// Takes the most recently created space that `wh` fits into. If both orientations
+    /// fit, the flipped one is only taken if it leaves fewer spaces behind.
+    fn first_fit<R: OutputRect<T>>(&self, wh: RectWH<T>) -> Option<Choice<T>> {
         for i in (0..self.spaces.get_count()).rev() {
             let candidate_space = self.spaces.get(i);
-            let normal = Self::try_to_insert(image_rectangle, candidate_space);
+            let normal = self.try_to_insert(wh, candidate_space);
+
+            if R::ALLOW_FLIP {
+                let flipped = self.try_to_insert(wh.flipped(), candidate_space);
+
+                if flipped.is_valid() && (!normal.is_valid() || flipped.better_than(&normal)) {
+                    return Some((i, candidate_space, flipped, true));
+                }
+            }
 
             if normal.is_valid() {
-                return self.accept_result(i, image_rectangle, candidate_space, &normal);
+                return Some((i, candidate_space, normal, false));
             }
         }
 
         None
     }
 
-    fn accept_result(
-        &mut self,
-        i: usize,
-        image_rectangle: RectWH,
-        candidate_space: RectXYWH,
-        splits: &CreatedSplits,
-    ) -> Option<RectXYWH> {
+    /// Scores both orientations in every space, and takes the lowest score.
+    /// Ties go to the orientation that leaves fewer spaces behind, then to the later space.
+    fn best_fit<R: OutputRect<T>>(&self, wh: RectWH<T>) -> Option<Choice<T>> {
+        let mut best = None;
+        let mut best_score = None;
+
+        for i in (0..self.spaces.get_count()).rev() {
+            let candidate_space = self.spaces.get(i);
+
+            for flipped in [false, true] {
+                if flipped && !R::ALLOW_FLIP {
+                    continue;
+                }
+
+                let wh = if flipped { wh.flipped() } else { wh };
+                let splits = self.try_to_insert(wh, candidate_space);
+
+                if !splits.is_valid() {
+                    continue;
+                }
+
+                let score = (self.score(wh, candidate_space), splits.count);
+
+                if best_score.as_ref().is_none_or(|best| score < *best) {
+                    best = Some((i, candidate_space, splits, flipped));
+                    best_score = Some(score);
+                }
+            }
+        }
+
+        best
+    }
+
+    /// How well `wh` fits into `space` under the current `SpaceSelection`. Lower is better.
+    fn score(&self, wh: RectWH<T>, space: RectXYWH<T>) -> (TotalAreaType<T>, TotalAreaType<T>) {
+        let leftover_w = (space.w - wh.w).to_area();
+        let leftover_h = (space.h - wh.h).to_area();
+
+        let (short_side, long_side) = if leftover_w < leftover_h {
+            (leftover_w, leftover_h)
+        } else {
+            (leftover_h, leftover_w)
+        };
+
+        match self.settings.selection {
+            SpaceSelection::ReverseFirstFit => Default::default(),
+            SpaceSelection::BestAreaFit => (space.area() - wh.area(), short_side),
+            SpaceSelection::BestShortSideFit => (short_side, long_side),
+            SpaceSelection::BestLongSideFit => (long_side, short_side),
+            SpaceSelection::BottomLeft => ((space.y + wh.h).to_area(), space.x.to_area()),
+        }
+    }
+
+    /// Replaces space `i` with the ones it was split into.
+    fn accept_result(&mut self, i: usize, splits: &CreatedSplits<T>) -> Result<(), PackError> {
+        // Check before touching anything, so that a full provider is left as it was.
+        let needed = splits.count as usize;
+
+        if self
+            .spaces
+            .remaining_capacity()
+            .is_some_and(|remaining| remaining + 1 < needed)
+        {
+            return Err(PackError::ProviderCapacityExceeded);
+        }
+
         self.spaces.remove(i);
 
         for s in 0..splits.count {
-            if !self
-                .spaces
-                .add(unsafe { splits.spaces[s as usize].assume_init() })
-            {
-                return None;
+            if !self.spaces.add(splits.spaces[s as usize]) {
+                return Err(PackError::ProviderCapacityExceeded);
             }
         }
 
-        let result = RectXYWH::new(
-            candidate_space.x,
-            candidate_space.y,
-            image_rectangle.w,
-            image_rectangle.h,
+        Ok(())
+    }
+
+    /// Empties the packer, leaving a single space that covers `r` without the edge margin.
+    pub fn reset(&mut self, r: RectWH<T>) {
+        self.current_aabb = Default::default();
+        self.bin = r;
+
+        self.spaces.reset();
+        self.spaces.add(free_space(r, self.settings.padding));
+    }
+
+    /// Cuts a fixed, pre-placed `region` out of the free space, so that nothing
+    /// gets inserted over it until the next `reset`. Meant to be called right after
+    /// `reset`, e.g. for a white pixel or a logo that has to stay in place.
+    /// The region is kept apart from the inserted rectangles by the spacing,
+    /// and is included in `get_rects_aabb`.
+    ///
+    /// Fails if the provider would run out of room, in which case nothing is changed.
+    pub fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        let spacing = self.settings.padding.spacing;
+        let cut = RectXYWH::new(region.x, region.y, region.w + spacing, region.h + spacing);
+
+        let cut_spaces = (0..self.spaces.get_count())
+            .filter(|&i| self.spaces.get(i).intersects(cut))
+            .collect::<Vec<_>>();
+
+        let mut pieces = Vec::new();
+
+        for &i in &cut_spaces {
+            cut_out(self.spaces.get(i), cut, &mut pieces);
+        }
+
+        if self
+            .spaces
+            .remaining_capacity()
+            .is_some_and(|remaining| remaining + cut_spaces.len() < pieces.len())
+        {
+            return Err(PackError::ProviderCapacityExceeded);
+        }
+
+        // Removing moves the last space into the gap, so go from the back.
+        for &i in cut_spaces.iter().rev() {
+            self.spaces.remove(i);
+        }
+
+        for piece in pieces {
+            if !self.spaces.add(piece) {
+                return Err(PackError::ProviderCapacityExceeded);
+            }
+        }
+
+        expand_with_reserved(
+            &mut self.current_aabb,
+            region,
+            self.settings.padding.edge_margin,
+            self.bin,
         );
 
-        self.current_aabb.expand_with(result);
+        Ok(())
+    }
+
+    /// Gives `region` back to the free space, merging it with every free space that
+    /// shares a whole edge with it. Spaces that only partly touch it stay separate, so
+    /// the free space fragments as regions are freed. `region` must not overlap any free space.
+    ///
+    /// Fails if the provider ran out of room, in which case the region is lost.
+    pub fn free(&mut self, mut region: RectXYWH<T>) -> Result<(), PackError> {
+        while let Some(i) = (0..self.spaces.get_count())
+            .rev()
+            .find(|&i| Self::shares_edge(region, self.spaces.get(i)))
+        {
+            let space = self.spaces.get(i);
+            self.spaces.remove(i);
+
+            region = RectXYWH::new(
+                coord::min(region.x, space.x),
+                coord::min(region.y, space.y),
+                if region.y == space.y && region.h == space.h {
+                    region.w + space.w
+                } else {
+                    region.w
+                },
+                if region.x == space.x && region.w == space.w {
+                    region.h + space.h
+                } else {
+                    region.h
+                },
+            );
+        }
 
-        Some(result)
+        if self.spaces.add(region) {
+            Ok(())
+        } else {
+            Err(PackError::ProviderCapacityExceeded)
+        }
     }
 
-    pub fn reset(&mut self, r: RectWH) {
-        self.current_aabb = Default::default();
+    fn shares_edge(a: RectXYWH<T>, b: RectXYWH<T>) -> bool {
+        let side_by_side = a.y == b.y && a.h == b.h && (a.x + a.w == b.x || b.x + b.w == a.x);
+        let stacked = a.x == b.x && a.w == b.w && (a.y + a.h == b.y || b.y + b.h == a.y);
 
-        self.spaces.reset();
-        self.spaces.add(RectXYWH::from_wh(r.w, r.h));
+        side_by_side || stacked
+    }
+
+    /// Sets the padding used by subsequent calls to `reset` and `insert`.
+    pub fn set_padding(&mut self, padding: Padding<T>) {
+        self.settings.padding = padding;
+    }
+
+    pub fn get_padding(&self) -> Padding<T> {
+        self.settings.padding
+    }
+
+    /// Sets the rule used by subsequent calls to `insert` for splitting the free spaces.
+    pub fn set_split_rule(&mut self, split_rule: SplitRule) {
+        self.settings.split_rule = split_rule;
+    }
+
+    pub fn get_split_rule(&self) -> SplitRule {
+        self.settings.split_rule
+    }
+
+    /// Sets the strategy used by subsequent calls to `insert` for picking an empty space.
+    pub fn set_selection(&mut self, selection: SpaceSelection) {
+        self.settings.selection = selection;
+    }
+
+    pub fn get_selection(&self) -> SpaceSelection {
+        self.settings.selection
+    }
+
+    pub fn get_settings(&self) -> EmptySpacesSettings<T> {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: EmptySpacesSettings<T>) {
+        self.settings = settings;
     }
 
-    fn try_to_insert(img: RectWH, candidate_space: RectXYWH) -> CreatedSplits {
-        insert_and_split(img, candidate_space)
+    fn try_to_insert(&self, img: RectWH<T>, candidate_space: RectXYWH<T>) -> CreatedSplits<T> {
+        insert_and_split(img, candidate_space, self.settings.split_rule)
     }
 
-    pub fn get_rects_aabb(&self) -> RectWH {
+    pub fn get_rects_aabb(&self) -> RectWH<T> {
         self.current_aabb
     }
 
@@ -0,0 +1,54 @@
+use std::{error::Error, fmt};
+
+/// Everything that can go wrong while packing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackError {
+    /// The `EmptySpacesProviderTrait` implementation ran out of room for empty spaces,
+    /// e.g. because `StaticEmptySpaces` was given a too small `MAX_SPACES`.
+    ProviderCapacityExceeded,
+    /// None of the rectangles have a non-zero area.
+    NoRects,
+    /// No ordering functions were passed in, so there's nothing to search.
+    NoOrderings,
+    /// The rectangle at `index` has a negative, or otherwise unusable size.
+    InvalidSize { index: usize },
+    /// The maximum bin has a non-positive, or otherwise unusable size,
+    /// or the bin size policy allows no size that fits into it.
+    InvalidMaxBin,
+    /// The spacing or the edge margin is negative, or otherwise unusable.
+    InvalidPadding,
+    /// The padding of the rectangle at `index` is negative, or otherwise unusable.
+    InvalidRectPadding { index: usize },
+    /// The reserved region at `index` has a negative size, or doesn't fit into the maximum bin.
+    InvalidReserved { index: usize },
+    /// The value at `index` is negative, or not finite.
+    InvalidValue { index: usize },
+    /// There's no empty space the rectangle fits into.
+    DidNotFit,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProviderCapacityExceeded => f.write_str("ran out of room for empty spaces"),
+            Self::NoRects => f.write_str("there are no rectangles to pack"),
+            Self::NoOrderings => f.write_str("there are no orderings to try"),
+            Self::InvalidSize { index } => write!(f, "rectangle {index} has an invalid size"),
+            Self::InvalidMaxBin => f.write_str("the maximum bin has an invalid size"),
+            Self::InvalidPadding => f.write_str("the spacing or the edge margin is invalid"),
+            Self::InvalidRectPadding { index } => {
+                write!(f, "rectangle {index} has an invalid padding")
+            }
+            Self::InvalidReserved { index } => {
+                write!(
+                    f,
+                    "reserved region {index} doesn't fit into the maximum bin"
+                )
+            }
+            Self::InvalidValue { index } => write!(f, "value {index} is invalid"),
+            Self::DidNotFit => f.write_str("the rectangle didn't fit"),
+        }
+    }
+}
+
+impl Error for PackError {}
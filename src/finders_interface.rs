@@ -1,57 +1,341 @@
-use std::{cmp::Ordering, mem::MaybeUninit};
+use std::cmp::Ordering;
 
 use crate::{
-    best_bin_finder::{CallbackResult, find_best_packing_impl},
-    empty_spaces::{EmptySpaces, EmptySpacesProviderTrait},
-    rect_structs::{RectWH, RectXYWH},
+    best_bin_finder::{
+        BinSizePolicy, CallbackResult, SearchMode, Subject, find_best_packing_impl,
+        find_best_packing_multi_impl, make_orders,
+    },
+    coord::{Coord, is_non_negative, is_positive},
+    empty_spaces::Padding,
+    error::PackError,
+    insert_and_split::SplitRule,
+    packer::Packer,
+    rect_structs::{OutputRect, Packable, RectWH, RectXYWH, TotalAreaType},
 };
 
-pub struct Input<F: Fn(RectXYWH) -> CallbackResult, G: Fn(RectXYWH) -> CallbackResult> {
-    pub max_bin_side: i32,
+/// Settings for the algorithm. The callbacks receive the rectangle type
+/// that is being packed, i.e. `RectXYWH` or `RectXYWHF`.
+pub struct Input<F, G, T: Coord = i32> {
+    /// The largest bin to try, each side is limited on its own.
+    pub max_bin: RectWH<T>,
     pub discard_step: i32,
     pub handle_successful_insertion: F,
+    /// Receives rectangles that didn't fit the way they were passed in.
+    /// `pack` and `pack_ordered` pass a rectangle at 0, 0 with the size of the subject instead,
+    /// since their subjects aren't of the rectangle type being packed.
     pub handle_unsuccessful_insertion: G,
+    /// Spacing between rectangles, and margin from the bin border.
+    pub padding: Padding<T>,
+    /// Padding kept free on every side of individual rectangles, indexed like the subjects.
+    /// Rectangles without an entry get no extra padding.
+    pub rect_padding: Vec<T>,
+    /// Constraints on the size of the resulting bin.
+    pub bin_size_policy: BinSizePolicy<T>,
+    /// Search the orderings on multiple threads. The result is the same as with
+    /// a sequential search, but every thread gets its own packer created from
+    /// the settings of `root`, which is only used for the final placement.
+    pub parallel: bool,
+    /// Split rules for the search to try with every ordering. If empty, the rule
+    /// set on `root` is used. Otherwise, the best one is left set on `root`.
+    /// Only packers that split free spaces the way `insert_and_split` does use this.
+    pub split_rules: Vec<SplitRule>,
+    /// Fixed regions, such as a white pixel or a logo, that are cut out of every bin
+    /// before anything is packed around them. The search only tries bins that contain
+    /// all of them, and with multiple bins, every bin gets them.
+    pub reserved: Vec<RectXYWH<T>>,
+    /// Which sides of the bin the search shrinks. With `FixedWidth` or `FixedHeight`,
+    /// the pinned side of the resulting bin is that of `max_bin`.
+    pub search_mode: SearchMode,
+    /// What individual rectangles are worth, indexed like the subjects, e.g. their priority.
+    /// If not empty, whenever not everything fits into `max_bin`, the ordering that
+    /// packs the most value wins, instead of the one that packs the most area,
+    /// and orderings by value and by value per area are tried as well.
+    /// Rectangles without an entry are worth nothing.
+    ///
+    /// Rectangles that don't fit are skipped, so `handle_unsuccessful_insertion`
+    /// has to continue packing for the rest to be placed.
+    pub values: Vec<f64>,
 }
 
-impl<F: Fn(RectXYWH) -> CallbackResult, G: Fn(RectXYWH) -> CallbackResult> Input<F, G> {
+impl<F, G, T: Coord> Input<F, G, T> {
     pub fn new(
-        max_bin_side: i32,
+        max_bin_side: T,
         discard_step: i32,
         handle_successful_insertion: F,
         handle_unsuccessful_insertion: G,
     ) -> Self {
         Self {
-            max_bin_side,
+            max_bin: RectWH::new(max_bin_side, max_bin_side),
             discard_step,
             handle_successful_insertion,
             handle_unsuccessful_insertion,
+            padding: Padding::default(),
+            rect_padding: Vec::new(),
+            bin_size_policy: BinSizePolicy::Free,
+            parallel: false,
+            split_rules: Vec::new(),
+            reserved: Vec::new(),
+            search_mode: SearchMode::Both,
+            values: Vec::new(),
         }
     }
+
+    /// Replaces the square maximum bin given to `new`.
+    pub fn with_max_bin(mut self, max_bin: RectWH<T>) -> Self {
+        self.max_bin = max_bin;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: Padding<T>) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_rect_padding(mut self, rect_padding: Vec<T>) -> Self {
+        self.rect_padding = rect_padding;
+        self
+    }
+
+    pub fn with_bin_size_policy(mut self, bin_size_policy: BinSizePolicy<T>) -> Self {
+        self.bin_size_policy = bin_size_policy;
+        self
+    }
+
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    pub fn with_split_rules(mut self, split_rules: Vec<SplitRule>) -> Self {
+        self.split_rules = split_rules;
+        self
+    }
+
+    pub fn with_reserved(mut self, reserved: Vec<RectXYWH<T>>) -> Self {
+        self.reserved = reserved;
+        self
+    }
+
+    pub fn with_search_mode(mut self, search_mode: SearchMode) -> Self {
+        self.search_mode = search_mode;
+        self
+    }
+
+    pub fn with_values(mut self, values: Vec<f64>) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Everything but the callbacks, e.g. for storing it in a config file.
+    pub fn settings(&self) -> InputSettings<T> {
+        InputSettings {
+            max_bin: self.max_bin,
+            discard_step: self.discard_step,
+            padding: self.padding,
+            rect_padding: self.rect_padding.clone(),
+            bin_size_policy: self.bin_size_policy,
+            parallel: self.parallel,
+            split_rules: self.split_rules.clone(),
+            reserved: self.reserved.clone(),
+            search_mode: self.search_mode,
+            values: self.values.clone(),
+        }
+    }
+
+    /// Turns every non-zero-area rectangle into a `Subject`, keeping its index.
+    /// Fails if any of the settings or sizes is unusable, or if nothing is left to pack.
+    fn subjects(
+        &self,
+        sizes: impl Iterator<Item = RectWH<T>>,
+    ) -> Result<Vec<Subject<T>>, PackError> {
+        let usable = |bin: RectWH<T>| is_positive(bin.w) && is_positive(bin.h);
+
+        if !(usable(self.max_bin) && usable(self.bin_size_policy.round_down(self.max_bin))) {
+            return Err(PackError::InvalidMaxBin);
+        }
+
+        if !(is_non_negative(self.padding.spacing) && is_non_negative(self.padding.edge_margin)) {
+            return Err(PackError::InvalidPadding);
+        }
+
+        if let Some(index) = self.rect_padding.iter().position(|&p| !is_non_negative(p)) {
+            return Err(PackError::InvalidRectPadding { index });
+        }
+
+        for (index, r) in self.reserved.iter().enumerate() {
+            let inside = is_non_negative(r.x)
+                && is_non_negative(r.y)
+                && is_non_negative(r.w)
+                && is_non_negative(r.h)
+                && r.x + r.w <= self.max_bin.w
+                && r.y + r.h <= self.max_bin.h;
+
+            if !inside {
+                return Err(PackError::InvalidReserved { index });
+            }
+        }
+
+        if let Some(index) = self
+            .values
+            .iter()
+            .position(|v| !(v.is_finite() && *v >= 0.0))
+        {
+            return Err(PackError::InvalidValue { index });
+        }
+
+        let mut subjects = Vec::new();
+
+        for (index, wh) in sizes.enumerate() {
+            if !(is_non_negative(wh.w) && is_non_negative(wh.h)) {
+                return Err(PackError::InvalidSize { index });
+            }
+
+            if is_positive(wh.w) && is_positive(wh.h) {
+                subjects.push(Subject {
+                    index,
+                    wh,
+                    padding: self.rect_padding.get(index).copied().unwrap_or(T::ZERO),
+                    value: self.values.get(index).copied().unwrap_or(0.0),
+                });
+            }
+        }
+
+        if subjects.is_empty() {
+            return Err(PackError::NoRects);
+        }
+
+        Ok(subjects)
+    }
+}
+
+/// The settings of an `Input` without its callbacks, so that they can be stored and cloned.
+/// See `Input` for what the fields mean.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputSettings<T: Coord = i32> {
+    pub max_bin: RectWH<T>,
+    pub discard_step: i32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub padding: Padding<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rect_padding: Vec<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bin_size_policy: BinSizePolicy<T>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parallel: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub split_rules: Vec<SplitRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reserved: Vec<RectXYWH<T>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub search_mode: SearchMode,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub values: Vec<f64>,
 }
 
+impl<T: Coord> InputSettings<T> {
+    /// Like `Input::new`, without the callbacks.
+    pub fn new(max_bin_side: T, discard_step: i32) -> Self {
+        Input::new(max_bin_side, discard_step, (), ()).settings()
+    }
+
+    /// Turns the settings into an `Input` with the given callbacks.
+    pub fn into_input<F, G>(
+        self,
+        handle_successful_insertion: F,
+        handle_unsuccessful_insertion: G,
+    ) -> Input<F, G, T> {
+        Input {
+            max_bin: self.max_bin,
+            discard_step: self.discard_step,
+            handle_successful_insertion,
+            handle_unsuccessful_insertion,
+            padding: self.padding,
+            rect_padding: self.rect_padding,
+            bin_size_policy: self.bin_size_policy,
+            parallel: self.parallel,
+            split_rules: self.split_rules,
+            reserved: self.reserved,
+            search_mode: self.search_mode,
+            values: self.values,
+        }
+    }
+}
+
+/// An `Input` whose callbacks always continue packing.
+impl<R, T: Coord> From<InputSettings<T>>
+    for Input<fn(R) -> CallbackResult, fn(R) -> CallbackResult, T>
+{
+    fn from(settings: InputSettings<T>) -> Self {
+        settings.into_input(
+            |_| CallbackResult::ContinuePacking,
+            |_| CallbackResult::ContinuePacking,
+        )
+    }
+}
+
+/// What happened to a rectangle during packing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RectStatus {
+    Placed,
+    /// It didn't fit, or packing was aborted before it was placed.
+    Failed,
+    /// It has a zero area, so there was nothing to place.
+    Skipped,
+}
+
+/// The result of packing a slice of rectangles.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "R: serde::Serialize, T: serde::Serialize, T::Area: serde::Serialize",
+        deserialize = "R: serde::Deserialize<'de>, T: serde::Deserialize<'de>, T::Area: serde::Deserialize<'de>"
+    ))
+)]
+pub struct PackingResult<R, T: Coord = i32> {
+    /// The size of the bin.
+    pub bin: RectWH<T>,
+    /// Where every subject ended up, in the order they were passed in.
+    /// `None` for zero-area rectangles and ones that didn't fit.
+    pub placements: Vec<Option<R>>,
+    /// What happened to every subject, in the order they were passed in.
+    pub statuses: Vec<RectStatus>,
+    /// The index of the ordering that won, among the ones that were passed in.
+    /// When packing by value, the orderings by value follow these.
+    pub best_order: usize,
+    /// The total area of the placed rectangles, without any padding.
+    pub used_area: TotalAreaType<T>,
+    /// `used_area` divided by the area of the bin.
+    pub occupancy: f64,
+    /// How many times the rectangles were packed, including the final placement.
+    pub trial_packings: usize,
+}
+
+impl<R, T: Coord> PackingResult<R, T> {
+    /// Whether every rectangle with a non-zero area was placed.
+    pub fn all_placed(&self) -> bool {
+        !self.statuses.contains(&RectStatus::Failed)
+    }
+}
+
+/// Like `find_best_packing_ordered`, but keeps the rectangles in the order they were passed in.
 pub fn find_best_packing_dont_sort<
     'a,
-    EmptySpacesType: EmptySpacesProviderTrait,
-    T: Iterator<Item = &'a mut RectXYWH>,
-    F: Fn(RectXYWH) -> CallbackResult,
-    G: Fn(RectXYWH) -> CallbackResult,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    I: Iterator<Item = &'a mut R>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
 >(
-    root: &mut EmptySpaces<EmptySpacesType>,
-    subjects: T,
-    input: &Input<F, G>,
-) -> RectWH {
-    let sortable = subjects
-        .filter_map(|f| {
-            let r: &mut RectXYWH = f.into();
-            if r.area() > 0 {
-                Some(r as *mut RectXYWH)
-            } else {
-                None
-            }
-        })
-        .collect::<Box<_>>();
-
-    find_best_packing_impl(root, &sortable, sortable.len(), input)
+    root: &mut PackerType,
+    subjects: I,
+    input: &Input<F, G, T>,
+) -> Result<PackingResult<R, T>, PackError> {
+    pack_into(root, subjects, input, &[|_: &R, _: &R| Ordering::Equal])
 }
 
 /// Forwards to `find_best_packing_ordered` with the following functions:
@@ -60,102 +344,346 @@ pub fn find_best_packing_dont_sort<
 /// - `|l, r| l.w.max(l.h).cmp(&r.w.max(r.h))`
 /// - `|l, r| l.w.cmp(&r.w)`
 /// - `|l, r| l.h.cmp(&r.h)`
+///
+/// These are applied to [`Packable::get_wh()`], so the rectangles can
+/// be either `RectXYWH`, or `RectXYWHF` if flipping should be allowed.
 pub fn find_best_packing<
     'a,
-    EmptySpacesType: EmptySpacesProviderTrait,
-    T: Iterator<Item = &'a mut RectXYWH>,
-    F: Fn(RectXYWH) -> CallbackResult,
-    G: Fn(RectXYWH) -> CallbackResult,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    I: Iterator<Item = &'a mut R>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: I,
+    input: &Input<F, G, T>,
+) -> Result<PackingResult<R, T>, PackError> {
+    pack_into(root, subjects, input, &default_orders())
+}
+
+/// Finds the best packing for a set of rectangles.
+/// Accepts any iterator that returns `&mut RectXYWH` or `&mut RectXYWHF`.
+///
+/// * `root` - Auxiliary storage for the algorithm.
+/// * `subjects` - The rectangles to pack. Their `x` and `y` components are filled in.
+/// * `input` - Settings for the algorithm.
+/// * `orders` - Ordering functions to use.
+///
+/// Returns the bin size, along with what happened to every rectangle
+/// and some statistics about the search, see [`PackingResult`].
+/// Rectangles that don't fit are reported to `handle_unsuccessful_insertion`,
+/// and don't make the packing fail. It fails if a size is negative (see [`PackError`]),
+/// if there's nothing with a non-zero area to pack, if `orders` is empty,
+/// or if `root` runs out of room.
+pub fn find_best_packing_ordered<
+    'a,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    I: Iterator<Item = &'a mut R>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+    const N: usize,
 >(
-    root: &mut EmptySpaces<EmptySpacesType>,
-    subjects: T,
-    input: &Input<F, G>,
-) -> RectWH {
-    find_best_packing_ordered(
+    root: &mut PackerType,
+    subjects: I,
+    input: &Input<F, G, T>,
+    orders: [fn(R, R) -> Ordering; N],
+) -> Result<PackingResult<R, T>, PackError> {
+    pack_into(
         root,
         subjects,
         input,
-        [
-            |l, r| l.area().cmp(&r.area()),
-            |l, r| l.perimeter().cmp(&r.perimeter()),
-            |l, r| l.w.max(l.h).cmp(&r.w.max(r.h)),
-            |l, r| l.w.cmp(&r.w),
-            |l, r| l.h.cmp(&r.h),
-        ],
+        &orders.map(|o| move |l: &R, r: &R| o(*l, *r)),
     )
 }
 
-/// Finds the best packing for a set of rectangles.
-/// Accepts any iterator that returns `&mut RectXYWH`, but its implementation
-/// of [`Iterator::size_hint()`] **must return a value as part of its upper bound**.
-/// This is important for optimizing allocations, the function panics otherwise.
+/// Forwards to `pack_ordered` with the same functions as `find_best_packing`.
+pub fn pack<
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T>,
+    P: Packable<T>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: &[P],
+    input: &Input<F, G, T>,
+) -> Result<PackingResult<R, T>, PackError> {
+    pack_impl(root, subjects, input, &default_orders(), unplaced(subjects))
+}
+
+/// Finds the best packing for a set of rectangles, without modifying them.
+/// Unlike `find_best_packing_ordered`, this accepts any type that implements
+/// [`Packable`], and returns the placements instead of writing them back.
+///
+/// * `root` - Auxiliary storage for the algorithm.
+/// * `subjects` - The rectangles to pack.
+/// * `input` - Settings for the algorithm.
+/// * `orders` - Ordering functions to use.
+pub fn pack_ordered<
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T>,
+    P: Packable<T>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+    const N: usize,
+>(
+    root: &mut PackerType,
+    subjects: &[P],
+    input: &Input<F, G, T>,
+    orders: [fn(&P, &P) -> Ordering; N],
+) -> Result<PackingResult<R, T>, PackError> {
+    pack_impl(root, subjects, input, &orders, unplaced(subjects))
+}
+
+/// How `pack` reports subjects that couldn't be placed, since they aren't of the output type.
+fn unplaced<T: Coord, R: OutputRect<T>, P: Packable<T>>(subjects: &[P]) -> impl Fn(usize) -> R {
+    |i| {
+        let wh = subjects[i].get_wh();
+        R::placed(T::ZERO, T::ZERO, wh.w, wh.h, false)
+    }
+}
+
+/// How a single bin of a `MultiBinPacking` turned out.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T::Area: serde::Serialize",
+        deserialize = "T::Area: serde::Deserialize<'de>"
+    ))
+)]
+pub struct BinStats<T: Coord = i32> {
+    /// The index of the ordering that won for this bin, as in `PackingResult`.
+    pub best_order: usize,
+    /// The total area of the rectangles placed into this bin, without any padding.
+    pub used_area: TotalAreaType<T>,
+    /// `used_area` divided by the area of the bin.
+    pub occupancy: f64,
+    /// How many times the rectangles were packed while filling this bin.
+    pub trial_packings: usize,
+}
+
+/// The result of packing into multiple bins.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Area: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Area: serde::Deserialize<'de>"
+    ))
+)]
+pub struct MultiBinPacking<T: Coord = i32> {
+    /// The size of every bin, in the order they were filled.
+    pub bins: Vec<RectWH<T>>,
+    /// The bin index of every subject, in the order they were passed in.
+    /// `None` for zero-area rectangles and ones that don't fit even an empty bin.
+    pub pages: Vec<Option<usize>>,
+    /// What happened to every subject, in the order they were passed in.
+    pub statuses: Vec<RectStatus>,
+    /// How every bin turned out, in the same order as `bins`.
+    pub stats: Vec<BinStats<T>>,
+}
+
+impl<T: Coord> MultiBinPacking<T> {
+    /// Whether every rectangle with a non-zero area was placed.
+    pub fn all_placed(&self) -> bool {
+        !self.statuses.contains(&RectStatus::Failed)
+    }
+
+    /// The total area of the placed rectangles, over all bins.
+    pub fn used_area(&self) -> TotalAreaType<T> {
+        self.stats
+            .iter()
+            .fold(TotalAreaType::<T>::default(), |sum, stats| {
+                sum + stats.used_area
+            })
+    }
+
+    /// How many times the rectangles were packed, over all bins.
+    pub fn trial_packings(&self) -> usize {
+        self.stats.iter().map(|stats| stats.trial_packings).sum()
+    }
+}
+
+/// Forwards to `find_best_packing_multi_ordered` with
+/// the same functions as `find_best_packing`.
+pub fn find_best_packing_multi<
+    'a,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    I: Iterator<Item = &'a mut R>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: I,
+    input: &Input<F, G, T>,
+) -> Result<MultiBinPacking<T>, PackError> {
+    pack_multi_into(root, subjects, input, &default_orders())
+}
+
+/// Finds the best packing for a set of rectangles, using as many bins as necessary.
+/// Each bin is at most `max_bin` big, and is searched for the same way
+/// `find_best_packing_ordered` does it, using the rectangles that didn't fit
+/// into any of the previous bins.
+///
+/// `handle_unsuccessful_insertion` is only called for rectangles that
+/// don't fit even an empty bin.
 ///
 /// * `root` - Auxiliary storage for the algorithm.
 /// * `subjects` - The rectangles to pack. Their `x` and `y` components are filled in.
 /// * `input` - Settings for the algorithm.
 /// * `orders` - Ordering functions to use.
-pub fn find_best_packing_ordered<
+pub fn find_best_packing_multi_ordered<
     'a,
-    EmptySpacesType: EmptySpacesProviderTrait,
-    T: Iterator<Item = &'a mut RectXYWH>,
-    F: Fn(RectXYWH) -> CallbackResult,
-    G: Fn(RectXYWH) -> CallbackResult,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    I: Iterator<Item = &'a mut R>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
     const N: usize,
 >(
-    root: &mut EmptySpaces<EmptySpacesType>,
-    subjects: T,
-    input: &Input<F, G>,
-    orders: [fn(RectXYWH, RectXYWH) -> Ordering; N],
-) -> RectWH {
-    let size_hint = subjects.size_hint().1.expect("No upper bound on size_hint");
-    let mut buffer = Box::<[*mut RectXYWH]>::new_uninit_slice(size_hint * N);
-    let (orders, chunk_size) = process_rects(subjects, &mut buffer, orders);
-
-    find_best_packing_impl(root, orders, chunk_size, input)
-}
-
-/// Takes a slice of uninitialized rects, fills + sorts all chunks,
-/// and returns the actual usable initialized part of the slice,
-/// as well as the chunk size (a.k.a. the number of non-zero-area rects).
-fn process_rects<'a, 'b, T: Iterator<Item = &'a mut RectXYWH>, const N: usize>(
-    subjects: T,
-    orders: &'b mut [MaybeUninit<*mut RectXYWH>],
-    orderers: [fn(RectXYWH, RectXYWH) -> Ordering; N],
-) -> (&'b [*mut RectXYWH], usize) {
-    let mut n_valid = 0;
-    for s in subjects {
-        let r: &mut RectXYWH = s.into();
-
-        if r.area() > 0 {
-            orders[n_valid].write(r as *mut RectXYWH);
-            n_valid += 1;
-        }
+    root: &mut PackerType,
+    subjects: I,
+    input: &Input<F, G, T>,
+    orders: [fn(R, R) -> Ordering; N],
+) -> Result<MultiBinPacking<T>, PackError> {
+    pack_multi_into(
+        root,
+        subjects,
+        input,
+        &orders.map(|o| move |l: &R, r: &R| o(*l, *r)),
+    )
+}
+
+fn default_orders<T: Coord, P: Packable<T>>() -> [fn(&P, &P) -> Ordering; 5] {
+    [
+        |l, r| compare(l.get_wh().area(), r.get_wh().area()),
+        |l, r| compare(l.get_wh().perimeter(), r.get_wh().perimeter()),
+        |l, r| compare(l.get_wh().max_side(), r.get_wh().max_side()),
+        |l, r| compare(l.get_wh().w, r.get_wh().w),
+        |l, r| compare(l.get_wh().h, r.get_wh().h),
+    ]
+}
+
+/// `PartialOrd` comparison that treats incomparable values (i.e. NaN) as equal.
+fn compare<V: PartialOrd>(l: V, r: V) -> Ordering {
+    l.partial_cmp(&r).unwrap_or(Ordering::Equal)
+}
+
+/// Converts orderers of the caller's type to ones that work on `Subject`s.
+fn subject_orderers<'a, T: Coord, P>(
+    subjects: &'a [P],
+    orderers: &'a [impl Fn(&P, &P) -> Ordering],
+) -> Vec<impl Fn(&Subject<T>, &Subject<T>) -> Ordering + 'a> {
+    orderers
+        .iter()
+        .map(|o| move |l: &Subject<T>, r: &Subject<T>| o(&subjects[l.index], &subjects[r.index]))
+        .collect()
+}
+
+fn pack_impl<
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T>,
+    P: Packable<T>,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: &[P],
+    input: &Input<F, G, T>,
+    orderers: &[impl Fn(&P, &P) -> Ordering],
+    original: impl Fn(usize) -> R,
+) -> Result<PackingResult<R, T>, PackError> {
+    if orderers.is_empty() {
+        return Err(PackError::NoOrderings);
     }
 
-    let (src, tgt) = orders.split_at_mut(n_valid);
-    src.sort_by(unsafe { s(orderers[0]) });
+    let valid = input.subjects(subjects.iter().map(Packable::get_wh))?;
+    let orders = make_orders(
+        &valid,
+        &subject_orderers(subjects, orderers),
+        !input.values.is_empty(),
+    );
 
-    for (chunk, o) in tgt
-        .chunks_exact_mut(n_valid)
-        .zip(orderers.iter().skip(1).copied())
-    {
-        chunk.copy_from_slice(src);
-        chunk.sort_by(unsafe { s(o) });
+    find_best_packing_impl(root, &orders, valid.len(), input, subjects.len(), original)
+}
+
+/// Runs `pack_impl` on copies of the rectangles, and writes the placements back.
+fn pack_into<
+    'a,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: impl Iterator<Item = &'a mut R>,
+    input: &Input<F, G, T>,
+    orderers: &[impl Fn(&R, &R) -> Ordering],
+) -> Result<PackingResult<R, T>, PackError> {
+    let mut subjects = subjects.collect::<Vec<_>>();
+    let rects = subjects.iter().map(|r| **r).collect::<Vec<_>>();
+
+    let result = pack_impl(root, &rects, input, orderers, |i| rects[i])?;
+    write_back(&mut subjects, &result.placements);
+
+    Ok(result)
+}
+
+fn pack_multi_into<
+    'a,
+    PackerType: Packer<T>,
+    T: Coord,
+    R: OutputRect<T> + 'a,
+    F: Fn(R) -> CallbackResult,
+    G: Fn(R) -> CallbackResult,
+>(
+    root: &mut PackerType,
+    subjects: impl Iterator<Item = &'a mut R>,
+    input: &Input<F, G, T>,
+    orderers: &[impl Fn(&R, &R) -> Ordering],
+) -> Result<MultiBinPacking<T>, PackError> {
+    if orderers.is_empty() {
+        return Err(PackError::NoOrderings);
     }
 
-    (unsafe { orders[..n_valid * N].assume_init_ref() }, n_valid)
+    let mut subjects = subjects.collect::<Vec<_>>();
+    let rects = subjects.iter().map(|r| **r).collect::<Vec<_>>();
+    let valid = input.subjects(rects.iter().map(Packable::get_wh))?;
+
+    let mut placements = vec![None; rects.len()];
+
+    let packing = find_best_packing_multi_impl(
+        root,
+        &valid,
+        input,
+        &subject_orderers(&rects, orderers),
+        &mut placements,
+        |i| rects[i],
+    )?;
+
+    write_back(&mut subjects, &placements);
+
+    Ok(packing)
 }
 
-/// Convenience function that converts a "user-facing" sort function
-/// to one that can be used with a slice of `MaybeUninit`. In other
-/// words, converts from `Fn(RectXYWH, RectXYWH) -> Ordering` to
-/// `Fn(&MaybeUninit<RectXYWH>, &MaybeUninit<RectXYWH>) -> Ordering`.
-///
-/// # Safety
-/// This function assumes that the `MaybeUninit` slice you're
-/// sorting with the returned function is fully initialized.
-unsafe fn s(
-    func: fn(RectXYWH, RectXYWH) -> Ordering,
-) -> impl Fn(&MaybeUninit<*mut RectXYWH>, &MaybeUninit<*mut RectXYWH>) -> Ordering {
-    move |lhs, rhs| func(unsafe { *lhs.assume_init() }, unsafe { *rhs.assume_init() }).reverse()
+fn write_back<T: Coord, R: OutputRect<T>>(subjects: &mut [&mut R], placements: &[Option<R>]) {
+    for (subject, placement) in subjects.iter_mut().zip(placements) {
+        if let Some(placement) = placement {
+            **subject = *placement;
+        }
+    }
 }
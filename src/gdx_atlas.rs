@@ -0,0 +1,465 @@
+use std::{
+    error::Error,
+    fmt::{self, Write},
+};
+
+use crate::{
+    finders_interface::{MultiBinPacking, PackingResult},
+    rect_structs::{OutputRect, RectWH, RectXYWH},
+};
+
+/// Everything that can go wrong while reading an `.atlas` file.
+/// Lines are counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// An entry that should have numbers, booleans or a known number of values doesn't.
+    InvalidValue { line: usize },
+    /// The region starting at `line` has no bounds.
+    MissingBounds { line: usize },
+    /// The page starting at `line` has no size.
+    MissingSize { line: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidValue { line } => write!(f, "line {line}: invalid value"),
+            Self::MissingBounds { line } => write!(f, "line {line}: the region has no bounds"),
+            Self::MissingSize { line } => write!(f, "line {line}: the page has no size"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A named sprite on a `Page`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub name: String,
+    /// The area covered in the page image. If `rotated` is set,
+    /// `w` and `h` are swapped relative to the sprite.
+    pub rect: RectXYWH,
+    /// Whether the sprite was rotated by 90 degrees counter-clockwise,
+    /// which is how libGDX and Spine expect rotated regions to be stored.
+    /// Note that TexturePacker's format, and thus `AtlasBuilder`, rotate clockwise,
+    /// which `GdxAtlas::from_image_atlas` takes care of.
+    pub rotated: bool,
+    /// Where the packed part starts within the original image, with `y`
+    /// measured from the bottom, as libGDX does it. Zero for untrimmed sprites.
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// The size of the sprite before transparent borders were trimmed off.
+    pub original_size: RectWH,
+    /// The index of an animation frame, or -1.
+    pub index: i32,
+    /// Nine-patch splits and padding, as left, right, top and bottom.
+    pub split: Option<[i32; 4]>,
+    pub pad: Option<[i32; 4]>,
+}
+
+impl Region {
+    /// An untrimmed region covering `rect` in the page image.
+    pub fn new(name: impl Into<String>, rect: RectXYWH, rotated: bool) -> Self {
+        let size = if rotated {
+            RectWH::new(rect.h, rect.w)
+        } else {
+            RectWH::new(rect.w, rect.h)
+        };
+
+        Self {
+            name: name.into(),
+            rect,
+            rotated,
+            offset_x: 0,
+            offset_y: 0,
+            original_size: size,
+            index: -1,
+            split: None,
+            pad: None,
+        }
+    }
+
+    /// The size of the sprite, before rotating it.
+    fn sprite_size(&self) -> RectWH {
+        if self.rotated {
+            RectWH::new(self.rect.h, self.rect.w)
+        } else {
+            RectWH::new(self.rect.w, self.rect.h)
+        }
+    }
+}
+
+/// One image of an atlas, along with the regions on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    /// The file name of the page image.
+    pub image: String,
+    pub size: RectWH,
+    /// The pixel format, e.g. `RGBA8888`.
+    pub format: String,
+    /// The minification and magnification filters, e.g. `Linear`.
+    pub filter: [String; 2],
+    /// `none`, `x`, `y` or `xy`.
+    pub repeat: String,
+    pub regions: Vec<Region>,
+}
+
+impl Page {
+    pub fn new(image: impl Into<String>, size: RectWH) -> Self {
+        Self {
+            image: image.into(),
+            size,
+            format: "RGBA8888".to_owned(),
+            filter: ["Nearest".to_owned(), "Nearest".to_owned()],
+            repeat: "none".to_owned(),
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.regions.push(region);
+        self
+    }
+}
+
+/// An atlas in the `.atlas` text format read by libGDX and Spine.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GdxAtlas {
+    pub pages: Vec<Page>,
+}
+
+impl GdxAtlas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A single page the size of the bin of `result`, with every placed rectangle
+    /// named after its entry in `names`. Rectangles that weren't placed are left out.
+    pub fn from_result<R: OutputRect>(
+        image: impl Into<String>,
+        result: &PackingResult<R>,
+        names: &[impl AsRef<str>],
+    ) -> Self {
+        let page = result
+            .placements
+            .iter()
+            .zip(names)
+            .filter_map(|(placement, name)| Some((placement.as_ref()?, name)))
+            .fold(Page::new(image, result.bin), |page, (placement, name)| {
+                page.with_region(Region::new(
+                    name.as_ref(),
+                    placement.get_xywh(),
+                    placement.flipped(),
+                ))
+            });
+
+        Self::new().with_page(page)
+    }
+
+    /// A page for every bin of `packing`, with the image name given by `image`.
+    /// `rects` are the subjects that were packed, and `names` name them.
+    /// Rectangles that weren't placed are left out.
+    pub fn from_multi<R: OutputRect>(
+        packing: &MultiBinPacking,
+        rects: &[R],
+        names: &[impl AsRef<str>],
+        image: impl Fn(usize) -> String,
+    ) -> Self {
+        let mut pages = packing
+            .bins
+            .iter()
+            .enumerate()
+            .map(|(i, &bin)| Page::new(image(i), bin))
+            .collect::<Vec<_>>();
+
+        for ((page, rect), name) in packing.pages.iter().zip(rects).zip(names) {
+            if let Some(page) = page.and_then(|p| pages.get_mut(p)) {
+                page.regions
+                    .push(Region::new(name.as_ref(), rect.get_xywh(), rect.flipped()));
+            }
+        }
+
+        Self { pages }
+    }
+
+    /// A single page with every sprite of an atlas built by `AtlasBuilder`, along with
+    /// the image to save for it. `AtlasBuilder` rotates sprites clockwise, so the rotated
+    /// ones are turned around in the returned image, which leaves them rotated counter-clockwise.
+    #[cfg(feature = "png")]
+    pub fn from_image_atlas(
+        image: impl Into<String>,
+        atlas: &crate::image_atlas::ImageAtlas,
+    ) -> (Self, crate::image_atlas::Image) {
+        let size = RectWH::new(atlas.image.width as i32, atlas.image.height as i32);
+        let mut pixels = atlas.image.clone();
+
+        let page = atlas
+            .sprites
+            .iter()
+            .fold(Page::new(image, size), |page, sprite| {
+                if sprite.rotated {
+                    pixels.turn_around(sprite.rect);
+                }
+
+                page.with_region(Region::new(
+                    sprite.name.as_str(),
+                    sprite.rect,
+                    sprite.rotated,
+                ))
+            });
+
+        (Self::new().with_page(page), pixels)
+    }
+
+    pub fn with_page(mut self, page: Page) -> Self {
+        self.pages.push(page);
+        self
+    }
+
+    /// Writes the atlas in the original libGDX format, which every libGDX and Spine runtime reads.
+    pub fn to_atlas(&self) -> String {
+        let mut out = String::new();
+        self.write_atlas(&mut out)
+            .expect("formatting an `.atlas` file into a `String` failed");
+        out
+    }
+
+    fn write_atlas(&self, out: &mut impl Write) -> fmt::Result {
+        for page in &self.pages {
+            writeln!(out)?;
+            writeln!(out, "{}", page.image)?;
+            writeln!(out, "size: {},{}", page.size.w, page.size.h)?;
+            writeln!(out, "format: {}", page.format)?;
+            writeln!(out, "filter: {},{}", page.filter[0], page.filter[1])?;
+            writeln!(out, "repeat: {}", page.repeat)?;
+
+            for region in &page.regions {
+                let size = region.sprite_size();
+
+                writeln!(out, "{}", region.name)?;
+                writeln!(out, "  rotate: {}", region.rotated)?;
+                writeln!(out, "  xy: {}, {}", region.rect.x, region.rect.y)?;
+                writeln!(out, "  size: {}, {}", size.w, size.h)?;
+
+                for (key, values) in [("split", region.split), ("pad", region.pad)] {
+                    if let Some([a, b, c, d]) = values {
+                        writeln!(out, "  {key}: {a}, {b}, {c}, {d}")?;
+                    }
+                }
+
+                writeln!(
+                    out,
+                    "  orig: {}, {}",
+                    region.original_size.w, region.original_size.h
+                )?;
+                writeln!(out, "  offset: {}, {}", region.offset_x, region.offset_y)?;
+                writeln!(out, "  index: {}", region.index)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads an atlas in either the original format, or the one introduced by libGDX 1.9.12
+    /// and Spine 4, which stores `bounds` and `offsets` instead. Unknown entries are skipped.
+    /// A region name may contain `:` as long as the region's entries are indented,
+    /// as `to_atlas` writes them.
+    pub fn from_atlas(text: &str) -> Result<Self, ParseError> {
+        let mut pages = Vec::new();
+        // The page being read, along with the line it started at.
+        let mut page: Option<(Page, usize)> = None;
+        let mut region: Option<RegionBuilder> = None;
+
+        let lines = text.lines().collect::<Vec<_>>();
+
+        for (i, &raw) in lines.iter().enumerate() {
+            let line_number = i + 1;
+            let line = raw.trim();
+
+            if line.is_empty() {
+                if let Some((mut finished, start)) = page.take() {
+                    finished
+                        .regions
+                        .extend(region.take().map(RegionBuilder::build).transpose()?);
+                    pages.push(check_size(finished, start)?);
+                }
+
+                continue;
+            }
+
+            // Region entries are indented, so an unindented line followed by an indented one
+            // names a region, even if there's a `:` in the name.
+            let names_region = page.is_some()
+                && !is_indented(raw)
+                && lines.get(i + 1).is_some_and(|next| is_indented(next));
+            let entry = line.split_once(':').filter(|_| !names_region);
+
+            let Some((key, value)) = entry else {
+                match &mut page {
+                    Some((page, _)) => {
+                        page.regions
+                            .extend(region.take().map(RegionBuilder::build).transpose()?);
+                        region = Some(RegionBuilder::new(line, line_number));
+                    }
+                    // A page with no size so far, which `check_size` catches.
+                    None => page = Some((Page::new(line, RectWH::new(-1, -1)), line_number)),
+                }
+
+                continue;
+            };
+
+            let key = key.trim();
+            let values = value.split(',').map(str::trim).collect::<Vec<_>>();
+            let invalid = ParseError::InvalidValue { line: line_number };
+
+            match (&mut region, &mut page) {
+                (Some(region), _) => region.set(key, &values).ok_or(invalid)?,
+                (None, Some((page, _))) => match key {
+                    "size" => {
+                        let [w, h] = numbers(&values).ok_or(invalid)?;
+                        page.size = RectWH::new(w, h);
+                    }
+                    "format" => page.format = values.join(","),
+                    "filter" => {
+                        let [min, mag] = values[..] else {
+                            return Err(invalid);
+                        };
+
+                        page.filter = [min.to_owned(), mag.to_owned()];
+                    }
+                    "repeat" => page.repeat = values.join(","),
+                    _ => {}
+                },
+                // Header entries before the first page, e.g. the scale written by Spine.
+                (None, None) => {}
+            }
+        }
+
+        if let Some((mut finished, start)) = page {
+            finished
+                .regions
+                .extend(region.map(RegionBuilder::build).transpose()?);
+            pages.push(check_size(finished, start)?);
+        }
+
+        Ok(Self { pages })
+    }
+}
+
+fn check_size(page: Page, start: usize) -> Result<Page, ParseError> {
+    if page.size.w < 0 || page.size.h < 0 {
+        Err(ParseError::MissingSize { line: start })
+    } else {
+        Ok(page)
+    }
+}
+
+fn is_indented(line: &str) -> bool {
+    line.starts_with([' ', '\t']) && !line.trim().is_empty()
+}
+
+/// Parses exactly `N` integers.
+fn numbers<const N: usize>(values: &[&str]) -> Option<[i32; N]> {
+    if values.len() != N {
+        return None;
+    }
+
+    let mut out = [0; N];
+
+    for (out, value) in out.iter_mut().zip(values) {
+        *out = value.parse().ok()?;
+    }
+
+    Some(out)
+}
+
+/// The entries of a region, collected until all of them are read.
+struct RegionBuilder {
+    name: String,
+    line: usize,
+    xy: Option<[i32; 2]>,
+    size: Option<[i32; 2]>,
+    orig: Option<[i32; 2]>,
+    offset: [i32; 2],
+    rotated: bool,
+    index: i32,
+    split: Option<[i32; 4]>,
+    pad: Option<[i32; 4]>,
+}
+
+impl RegionBuilder {
+    fn new(name: &str, line: usize) -> Self {
+        Self {
+            name: name.to_owned(),
+            line,
+            xy: None,
+            size: None,
+            orig: None,
+            offset: [0, 0],
+            rotated: false,
+            index: -1,
+            split: None,
+            pad: None,
+        }
+    }
+
+    /// Returns `None` if the values are invalid.
+    fn set(&mut self, key: &str, values: &[&str]) -> Option<()> {
+        match key {
+            "xy" => self.xy = Some(numbers(values)?),
+            "size" => self.size = Some(numbers(values)?),
+            "bounds" => {
+                let [x, y, w, h] = numbers(values)?;
+                self.xy = Some([x, y]);
+                self.size = Some([w, h]);
+            }
+            "orig" => self.orig = Some(numbers(values)?),
+            "offset" => self.offset = numbers(values)?,
+            "offsets" => {
+                let [x, y, w, h] = numbers(values)?;
+                self.offset = [x, y];
+                self.orig = Some([w, h]);
+            }
+            "rotate" => {
+                self.rotated = match values {
+                    ["true"] | ["90"] => true,
+                    ["false"] | ["0"] => false,
+                    // Only quarter turns are supported.
+                    _ => return None,
+                }
+            }
+            "index" => self.index = numbers::<1>(values)?[0],
+            "split" => self.split = Some(numbers(values)?),
+            "pad" => self.pad = Some(numbers(values)?),
+            _ => {}
+        }
+
+        Some(())
+    }
+
+    fn build(self) -> Result<Region, ParseError> {
+        let (Some([x, y]), Some([w, h])) = (self.xy, self.size) else {
+            return Err(ParseError::MissingBounds { line: self.line });
+        };
+
+        // The size is stored before rotating the sprite.
+        let rect = if self.rotated {
+            RectXYWH::new(x, y, h, w)
+        } else {
+            RectXYWH::new(x, y, w, h)
+        };
+        let [orig_w, orig_h] = self.orig.unwrap_or([w, h]);
+
+        Ok(Region {
+            name: self.name,
+            rect,
+            rotated: self.rotated,
+            offset_x: self.offset[0],
+            offset_y: self.offset[1],
+            original_size: RectWH::new(orig_w, orig_h),
+            index: self.index,
+            split: self.split,
+            pad: self.pad,
+        })
+    }
+}
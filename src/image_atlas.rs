@@ -0,0 +1,466 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    best_bin_finder::{BinSizePolicy, CallbackResult},
+    empty_space_allocators::DefaultEmptySpaces,
+    empty_spaces::{EmptySpaces, Padding},
+    error::PackError,
+    finders_interface::{Input, PackingResult, find_best_packing},
+    rect_structs::{OutputRect, RectWH, RectXYWH, RectXYWHF},
+};
+
+use serde::Serialize;
+
+/// Everything that can go wrong while building an atlas.
+#[derive(Debug)]
+pub enum AtlasError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Decode {
+        path: PathBuf,
+        error: png::DecodingError,
+    },
+    Encode {
+        path: PathBuf,
+        error: png::EncodingError,
+    },
+    /// The sprites couldn't be packed. `DidNotFit` means that some of them
+    /// didn't fit into the maximum bin.
+    Pack(PackError),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            Self::Decode { path, error } => write!(f, "{}: {error}", path.display()),
+            Self::Encode { path, error } => write!(f, "{}: {error}", path.display()),
+            Self::Pack(error) => write!(f, "couldn't pack the sprites: {error}"),
+        }
+    }
+}
+
+impl Error for AtlasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io { error, .. } => Some(error),
+            Self::Decode { error, .. } => Some(error),
+            Self::Encode { error, .. } => Some(error),
+            Self::Pack(error) => Some(error),
+        }
+    }
+}
+
+impl From<PackError> for AtlasError {
+    fn from(error: PackError) -> Self {
+        Self::Pack(error)
+    }
+}
+
+/// An 8-bit RGBA image, stored row by row.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// A fully transparent image.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Loads a PNG of any color type, converting it to 8-bit RGBA.
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, AtlasError> {
+        let path = path.as_ref();
+        let decode_error = |error| AtlasError::Decode {
+            path: path.to_owned(),
+            error,
+        };
+
+        let file = File::open(path).map_err(|error| AtlasError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // Palettes, low bit depths and 16-bit channels all become 8-bit channels.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().map_err(decode_error)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(decode_error)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            // Indexed images are expanded to RGB or RGBA by the transformations.
+            png::ColorType::Grayscale | png::ColorType::Indexed => {
+                buffer.iter().flat_map(|&p| [p, p, p, 255]).collect()
+            }
+        };
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        let encode_error = |error| AtlasError::Encode {
+            path: path.to_owned(),
+            error,
+        };
+
+        let file = File::create(path).map_err(|error| AtlasError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .map_err(encode_error)
+    }
+
+    /// Rotates the pixels within `rect` by 180 degrees.
+    #[cfg(feature = "libgdx")]
+    pub(crate) fn turn_around(&mut self, rect: RectXYWH) {
+        let width = self.width as usize;
+        let pixel = |x: i32, y: i32| (y as usize * width + x as usize) * 4;
+        let (w, h) = (rect.w, rect.h);
+
+        // Swaps every pixel in the first half with its mirror in the second one.
+        for i in 0..(w * h / 2) {
+            let (x, y) = (rect.x + i % w, rect.y + i / w);
+            let (mx, my) = (rect.x + w - 1 - i % w, rect.y + h - 1 - i / w);
+
+            let (a, b) = (pixel(x, y), pixel(mx, my));
+
+            for c in 0..4 {
+                self.pixels.swap(a + c, b + c);
+            }
+        }
+    }
+
+    /// Copies `source` with its top left corner at `x` and `y`,
+    /// rotating it by 90 degrees clockwise if `rotated` is set.
+    fn blit(&mut self, source: &Image, x: u32, y: u32, rotated: bool) {
+        for sy in 0..source.height {
+            for sx in 0..source.width {
+                let (dx, dy) = if rotated {
+                    (x + source.height - 1 - sy, y + sx)
+                } else {
+                    (x + sx, y + sy)
+                };
+
+                let from = (sy as usize * source.width as usize + sx as usize) * 4;
+                let to = (dy as usize * self.width as usize + dx as usize) * 4;
+
+                self.pixels[to..to + 4].copy_from_slice(&source.pixels[from..from + 4]);
+            }
+        }
+    }
+}
+
+/// A named image to put into an atlas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    pub name: String,
+    pub image: Image,
+}
+
+/// Loads every `.png` file directly inside `dir`, sorted by file name.
+/// Every sprite is named after its file, without the extension.
+pub fn load_sprites(dir: impl AsRef<Path>) -> Result<Vec<Sprite>, AtlasError> {
+    let dir = dir.as_ref();
+    let io_error = |error| AtlasError::Io {
+        path: dir.to_owned(),
+        error,
+    };
+
+    let mut paths = Vec::new();
+
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+
+        if is_png && path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            Ok(Sprite {
+                name: path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                image: Image::load_png(&path)?,
+            })
+        })
+        .collect()
+}
+
+/// Where a sprite ended up in an `ImageAtlas`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasSprite {
+    pub name: String,
+    /// The area covered in the atlas image. If `rotated` is set,
+    /// `w` and `h` are swapped relative to the sprite.
+    pub rect: RectXYWH,
+    /// Whether the sprite was rotated by 90 degrees clockwise.
+    pub rotated: bool,
+}
+
+/// A packed atlas image, along with where every sprite is in it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageAtlas {
+    pub image: Image,
+    /// In the order the sprites were passed in.
+    pub sprites: Vec<AtlasSprite>,
+}
+
+impl ImageAtlas {
+    /// Describes the atlas as JSON, with `image` as the name of the atlas image.
+    pub fn manifest(&self, image: &str) -> String {
+        let manifest = Manifest {
+            image,
+            size: ManifestSize {
+                w: self.image.width,
+                h: self.image.height,
+            },
+            sprites: self
+                .sprites
+                .iter()
+                .map(|sprite| ManifestSprite {
+                    name: &sprite.name,
+                    x: sprite.rect.x,
+                    y: sprite.rect.y,
+                    w: sprite.rect.w,
+                    h: sprite.rect.h,
+                    rotated: sprite.rotated,
+                })
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&manifest)
+            .expect("the manifest only has strings, numbers and booleans, which always serialize")
+    }
+
+    /// Saves the atlas image as a PNG, and the `manifest` next to it.
+    pub fn save(
+        &self,
+        image_path: impl AsRef<Path>,
+        manifest_path: impl AsRef<Path>,
+    ) -> Result<(), AtlasError> {
+        let image_path = image_path.as_ref();
+        let manifest_path = manifest_path.as_ref();
+
+        self.image.save_png(image_path)?;
+
+        let image_name = image_path.file_name().unwrap_or_default().to_string_lossy();
+
+        fs::write(manifest_path, self.manifest(&image_name)).map_err(|error| AtlasError::Io {
+            path: manifest_path.to_owned(),
+            error,
+        })
+    }
+}
+
+// Mirrors of the manifest.
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    image: &'a str,
+    size: ManifestSize,
+    sprites: Vec<ManifestSprite<'a>>,
+}
+
+#[derive(Serialize)]
+struct ManifestSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct ManifestSprite<'a> {
+    name: &'a str,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    rotated: bool,
+}
+
+/// Packs sprites with `find_best_packing`, and composites them into one image.
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasBuilder {
+    max_bin: RectWH,
+    discard_step: i32,
+    padding: Padding,
+    bin_size_policy: BinSizePolicy,
+    allow_flip: bool,
+}
+
+impl AtlasBuilder {
+    pub fn new(max_side: i32) -> Self {
+        Self {
+            max_bin: RectWH::new(max_side, max_side),
+            discard_step: 1,
+            padding: Padding::default(),
+            bin_size_policy: BinSizePolicy::Free,
+            allow_flip: false,
+        }
+    }
+
+    /// Replaces the square maximum bin given to `new`.
+    pub fn with_max_bin(mut self, max_bin: RectWH) -> Self {
+        self.max_bin = max_bin;
+        self
+    }
+
+    pub fn with_discard_step(mut self, discard_step: i32) -> Self {
+        self.discard_step = discard_step;
+        self
+    }
+
+    /// Spacing between sprites, e.g. to keep texture filtering from bleeding
+    /// them into each other, and margin from the atlas border.
+    pub fn with_padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_bin_size_policy(mut self, bin_size_policy: BinSizePolicy) -> Self {
+        self.bin_size_policy = bin_size_policy;
+        self
+    }
+
+    /// Allows rotating sprites by 90 degrees clockwise to save space.
+    pub fn with_allow_flip(mut self, allow_flip: bool) -> Self {
+        self.allow_flip = allow_flip;
+        self
+    }
+
+    /// Builds an atlas out of `sprites`. Fails with `PackError::DidNotFit`
+    /// unless every sprite fits into the maximum bin.
+    /// Empty sprites are kept in the manifest, with an empty rectangle.
+    pub fn build(&self, sprites: &[Sprite]) -> Result<ImageAtlas, AtlasError> {
+        let (bin, placements) = if self.allow_flip {
+            let result: PackingResult<RectXYWHF> = self.pack(sprites)?;
+            (result.bin, result.placements)
+        } else {
+            let result: PackingResult<RectXYWH> = self.pack(sprites)?;
+            let placements = result
+                .placements
+                .into_iter()
+                .map(|p| p.map(RectXYWHF::from))
+                .collect::<Vec<_>>();
+
+            (result.bin, placements)
+        };
+
+        let mut image = Image::new(bin.w as u32, bin.h as u32);
+
+        let sprites = sprites
+            .iter()
+            .zip(&placements)
+            .map(|(sprite, placement)| {
+                let placement = placement.unwrap_or_default();
+                image.blit(
+                    &sprite.image,
+                    placement.x as u32,
+                    placement.y as u32,
+                    placement.flipped,
+                );
+
+                AtlasSprite {
+                    name: sprite.name.clone(),
+                    rect: placement.get_xywh(),
+                    rotated: placement.flipped,
+                }
+            })
+            .collect();
+
+        Ok(ImageAtlas { image, sprites })
+    }
+
+    fn pack<R: OutputRect>(&self, sprites: &[Sprite]) -> Result<PackingResult<R>, AtlasError> {
+        let mut rects = Vec::with_capacity(sprites.len());
+
+        for (index, sprite) in sprites.iter().enumerate() {
+            // Sides that don't fit into a coordinate can't be packed anyway.
+            let (Ok(w), Ok(h)) = (
+                i32::try_from(sprite.image.width),
+                i32::try_from(sprite.image.height),
+            ) else {
+                return Err(PackError::InvalidSize { index }.into());
+            };
+
+            rects.push(R::placed(0, 0, w, h, false));
+        }
+
+        let input = Input::new(
+            0,
+            self.discard_step,
+            |_| CallbackResult::ContinuePacking,
+            |_| CallbackResult::ContinuePacking,
+        )
+        .with_max_bin(self.max_bin)
+        .with_padding(self.padding)
+        .with_bin_size_policy(self.bin_size_policy);
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing(&mut root, rects.iter_mut(), &input)?;
+
+        // Every sprite is copied into an image of the bin's size, so none may stick out of it.
+        let bin = RectXYWH::from_wh(result.bin.w, result.bin.h);
+        let inside = result
+            .placements
+            .iter()
+            .flatten()
+            .all(|placement| bin.contains(placement.get_xywh()));
+
+        if !result.all_placed() || !inside {
+            return Err(PackError::DidNotFit.into());
+        }
+
+        Ok(result)
+    }
+}
@@ -1,59 +1,111 @@
-use std::mem::MaybeUninit;
+use crate::{
+    coord::{self, Coord},
+    rect_structs::{RectWH, RectXYWH},
+};
+
+/// Which way `insert_and_split` cuts the space that is left next to an inserted rectangle,
+/// when there's some left both to the right of and below it.
+/// One of the two new spaces always spans the whole side of the old one.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SplitRule {
+    /// The space on the side with more room left spans the whole side.
+    /// This is what rectpack2D does.
+    #[default]
+    LongerLeftoverAxis,
+    /// The space on the side with less room left spans the whole side.
+    ShorterLeftoverAxis,
+    /// Makes the smaller of the two spaces as small as possible,
+    /// which keeps the bigger one as big as possible.
+    MinArea,
+    /// Makes the smaller of the two spaces as big as possible.
+    MaxArea,
+}
 
-use crate::rect_structs::{RectWH, RectXYWH};
+impl SplitRule {
+    pub const ALL: [Self; 4] = [
+        Self::LongerLeftoverAxis,
+        Self::ShorterLeftoverAxis,
+        Self::MinArea,
+        Self::MaxArea,
+    ];
+
+    /// Whether the space to the right of `im` should span the whole height of the space.
+    fn right_spans_height<T: Coord>(self, im: RectWH<T>, free_w: T, free_h: T) -> bool {
+        // The areas of the smaller space, if the right or the bottom one spans the whole side.
+        let below = im.w.to_area() * free_h.to_area();
+        let right = free_w.to_area() * im.h.to_area();
+
+        match self {
+            Self::LongerLeftoverAxis => free_w > free_h,
+            Self::ShorterLeftoverAxis => free_w < free_h,
+            Self::MinArea => below < right,
+            Self::MaxArea => below > right,
+        }
+    }
+}
 
-pub(crate) struct CreatedSplits {
+pub(crate) struct CreatedSplits<T: Coord> {
     pub count: i32,
-    pub spaces: [MaybeUninit<RectXYWH>; 2],
+    pub spaces: [RectXYWH<T>; 2],
 }
 
-impl CreatedSplits {
+impl<T: Coord> CreatedSplits<T> {
     pub fn failed() -> Self {
         Self {
             count: -1,
-            spaces: [MaybeUninit::uninit(); _],
+            spaces: Default::default(),
         }
     }
 
     pub fn none() -> Self {
         Self {
             count: 0,
-            spaces: [MaybeUninit::uninit(); _],
+            spaces: Default::default(),
         }
     }
 
-    pub fn unified(first: RectXYWH) -> Self {
+    pub fn unified(first: RectXYWH<T>) -> Self {
         Self {
             count: 1,
-            spaces: [MaybeUninit::new(first), MaybeUninit::uninit()],
+            spaces: [first, RectXYWH::default()],
         }
     }
 
-    pub fn split(first: RectXYWH, second: RectXYWH) -> Self {
+    pub fn split(first: RectXYWH<T>, second: RectXYWH<T>) -> Self {
         Self {
             count: 2,
-            spaces: [MaybeUninit::new(first), MaybeUninit::new(second)],
+            spaces: [first, second],
         }
     }
 
     pub fn is_valid(&self) -> bool {
         self.count != -1
     }
-}
 
-pub(crate) fn insert_and_split(im: RectWH, sp: RectXYWH) -> CreatedSplits {
-    let free_w = sp.w - im.w;
-    let free_h = sp.h - im.h;
+    /// Fewer leftover spaces are considered better.
+    pub fn better_than(&self, other: &Self) -> bool {
+        self.count < other.count
+    }
+}
 
-    if free_w < 0 || free_h < 0 {
+pub(crate) fn insert_and_split<T: Coord>(
+    im: RectWH<T>,
+    sp: RectXYWH<T>,
+    rule: SplitRule,
+) -> CreatedSplits<T> {
+    if im.w > sp.w || im.h > sp.h {
         return CreatedSplits::failed();
     }
 
-    if free_w == 0 && free_h == 0 {
+    let free_w = sp.w - im.w;
+    let free_h = sp.h - im.h;
+
+    if free_w == T::ZERO && free_h == T::ZERO {
         return CreatedSplits::none();
     }
 
-    if free_w > 0 && free_h == 0 {
+    if free_w > T::ZERO && free_h == T::ZERO {
         let mut r = sp;
         r.x += im.w;
         r.w -= im.w;
@@ -61,7 +113,7 @@ pub(crate) fn insert_and_split(im: RectWH, sp: RectXYWH) -> CreatedSplits {
         return CreatedSplits::unified(r);
     }
 
-    if free_w == 0 && free_h > 0 {
+    if free_w == T::ZERO && free_h > T::ZERO {
         let mut r = sp;
         r.y += im.h;
         r.h -= im.h;
@@ -69,7 +121,7 @@ pub(crate) fn insert_and_split(im: RectWH, sp: RectXYWH) -> CreatedSplits {
         return CreatedSplits::unified(r);
     }
 
-    if free_w > free_h {
+    if rule.right_spans_height(im, free_w, free_h) {
         let bigger_split = RectXYWH::new(sp.x + im.w, sp.y, free_w, sp.h);
         let lesser_split = RectXYWH::new(sp.x, sp.y + im.h, im.w, free_h);
 
@@ -81,3 +133,45 @@ pub(crate) fn insert_and_split(im: RectWH, sp: RectXYWH) -> CreatedSplits {
 
     CreatedSplits::split(bigger_split, lesser_split)
 }
+
+/// Pushes the up to four disjoint pieces that are left of `space` after cutting `region`
+/// out of it. The pieces above and below span the whole width of `space`.
+pub(crate) fn cut_out<T: Coord>(
+    space: RectXYWH<T>,
+    region: RectXYWH<T>,
+    out: &mut Vec<RectXYWH<T>>,
+) {
+    let space_right = space.x + space.w;
+    let space_bottom = space.y + space.h;
+    let region_right = region.x + region.w;
+    let region_bottom = region.y + region.h;
+
+    if region.y > space.y {
+        out.push(RectXYWH::new(space.x, space.y, space.w, region.y - space.y));
+    }
+
+    if region_bottom < space_bottom {
+        out.push(RectXYWH::new(
+            space.x,
+            region_bottom,
+            space.w,
+            space_bottom - region_bottom,
+        ));
+    }
+
+    let top = coord::max(space.y, region.y);
+    let h = coord::min(space_bottom, region_bottom) - top;
+
+    if region.x > space.x {
+        out.push(RectXYWH::new(space.x, top, region.x - space.x, h));
+    }
+
+    if region_right < space_right {
+        out.push(RectXYWH::new(
+            region_right,
+            top,
+            space_right - region_right,
+            h,
+        ));
+    }
+}
@@ -1,20 +1,74 @@
+#![forbid(unsafe_code)]
+
+pub mod atlas;
 pub mod best_bin_finder;
+pub mod coord;
 pub mod empty_space_allocators;
 pub mod empty_spaces;
+pub mod error;
 pub mod finders_interface;
+#[cfg(feature = "libgdx")]
+pub mod gdx_atlas;
+#[cfg(feature = "png")]
+pub mod image_atlas;
 pub mod insert_and_split;
+pub mod max_rects;
+pub mod packer;
 pub mod rect_structs;
+pub mod skyline;
+pub mod svg;
+#[cfg(feature = "texturepacker")]
+pub mod texture_packer;
+pub mod validate;
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        best_bin_finder::CallbackResult,
-        empty_space_allocators::DefaultEmptySpaces,
-        empty_spaces::EmptySpaces,
-        finders_interface::{Input, find_best_packing},
-        rect_structs::{RectWH, RectXYWH},
+        atlas::Atlas,
+        best_bin_finder::{BinSizePolicy, CallbackResult, SearchMode},
+        coord::Coord,
+        empty_space_allocators::{DefaultEmptySpaces, StaticEmptySpaces},
+        empty_spaces::{EmptySpaces, EmptySpacesProviderTrait, Padding, SpaceSelection},
+        error::PackError,
+        finders_interface::{
+            BinStats, Input, MultiBinPacking, PackingResult, RectStatus, find_best_packing,
+            find_best_packing_multi, pack,
+        },
+        insert_and_split::SplitRule,
+        max_rects::{MaxRects, MaxRectsHeuristic},
+        packer::Packer,
+        rect_structs::{Packable, RectWH, RectXYWH, RectXYWHF},
+        skyline::{Skyline, SkylineHeuristic},
+        svg::SvgExport,
+        validate::{Violation, validate_packing},
     };
 
+    /// Rectangles with sides from 1 to `max_side`, from a small LCG
+    /// so that the tests don't need a dependency.
+    fn random_rects(mut seed: u32, count: usize, max_side: i32) -> Vec<RectXYWHF> {
+        let mut next = move || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as i32 % max_side + 1
+        };
+
+        (0..count)
+            .map(|_| RectXYWHF::from_wh(next(), next()))
+            .collect()
+    }
+
+    /// Checks that the rectangles are inside `bin` and that no two of them touch,
+    /// as packed with both the spacing and the edge margin set to 1.
+    fn assert_spaced(rects: &[RectXYWHF], bin: RectWH) {
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.x >= 1 && a.y >= 1);
+            assert!(a.x + a.w < bin.w && a.y + a.h < bin.h);
+
+            for b in &rects[i + 1..] {
+                assert!(a.x + a.w < b.x || b.x + b.w < a.x || a.y + a.h < b.y || b.y + b.h < a.y);
+            }
+        }
+    }
+
     #[test]
     fn basic_usage() {
         let mut subjects = [
@@ -35,7 +89,9 @@ mod tests {
                 |_| CallbackResult::ContinuePacking,
                 |_| CallbackResult::AbortPacking,
             ),
-        );
+        )
+        .unwrap()
+        .bin;
 
         assert_eq!(result, RectWH::new(640, 512));
         assert_eq!(
@@ -54,18 +110,6 @@ mod tests {
         #[derive(Debug, PartialEq)]
         struct Foo(RectXYWH);
 
-        impl<'a> From<&'a Foo> for &'a RectXYWH {
-            fn from(value: &'a Foo) -> Self {
-                &value.0
-            }
-        }
-
-        impl<'a> From<&'a mut Foo> for &'a mut RectXYWH {
-            fn from(value: &'a mut Foo) -> Self {
-                &mut value.0
-            }
-        }
-
         let mut subjects = [
             Foo(RectXYWH::from_wh(100, 40)), // top
             Foo(RectXYWH::from_wh(40, 20)),  // center
@@ -85,7 +129,9 @@ mod tests {
                 |_| CallbackResult::ContinuePacking,
                 |_| CallbackResult::AbortPacking,
             ),
-        );
+        )
+        .unwrap()
+        .bin;
 
         assert_eq!(result, RectWH::new(100, 80));
         assert_eq!(
@@ -99,4 +145,1627 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn flipping() {
+        let mut subjects = [
+            RectXYWHF::from_wh(100, 20),
+            RectXYWHF::from_wh(20, 100),
+            RectXYWHF::from_wh(100, 20),
+            RectXYWHF::from_wh(20, 100),
+        ];
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                4096,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            ),
+        )
+        .unwrap()
+        .bin;
+
+        assert_eq!(result, RectWH::new(80, 100));
+        assert_eq!(
+            subjects,
+            [
+                RectXYWHF::new(40, 0, 100, 20, true),
+                RectXYWHF::new(0, 0, 20, 100, false),
+                RectXYWHF::new(60, 0, 100, 20, true),
+                RectXYWHF::new(20, 0, 20, 100, false),
+            ]
+        )
+    }
+
+    #[test]
+    fn multiple_bins() {
+        let mut subjects = [
+            RectXYWH::from_wh(128, 128),
+            RectXYWH::from_wh(128, 128),
+            RectXYWH::from_wh(300, 10),
+            RectXYWH::from_wh(128, 128),
+            RectXYWH::from_wh(0, 0),
+            RectXYWH::from_wh(128, 128),
+            RectXYWH::from_wh(64, 128),
+        ];
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing_multi(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                256,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            MultiBinPacking {
+                bins: vec![RectWH::new(256, 256), RectWH::new(64, 128)],
+                pages: vec![Some(0), Some(0), None, Some(0), None, Some(0), Some(1)],
+                statuses: vec![
+                    RectStatus::Placed,
+                    RectStatus::Placed,
+                    RectStatus::Failed,
+                    RectStatus::Placed,
+                    RectStatus::Skipped,
+                    RectStatus::Placed,
+                    RectStatus::Placed,
+                ],
+                stats: vec![
+                    BinStats {
+                        best_order: 0,
+                        used_area: 65536,
+                        occupancy: 1.0,
+                        trial_packings: 46,
+                    },
+                    BinStats {
+                        best_order: 0,
+                        used_area: 8192,
+                        occupancy: 1.0,
+                        trial_packings: 46,
+                    },
+                ],
+            }
+        );
+        assert!(!result.all_placed());
+        assert_eq!(result.used_area(), 65536 + 8192);
+        assert_eq!(result.trial_packings(), 92);
+        assert_eq!(
+            subjects,
+            [
+                RectXYWH::new(0, 0, 128, 128),
+                RectXYWH::new(128, 0, 128, 128),
+                RectXYWH::new(0, 0, 300, 10),
+                RectXYWH::new(0, 128, 128, 128),
+                RectXYWH::new(0, 0, 0, 0),
+                RectXYWH::new(128, 128, 128, 128),
+                RectXYWH::new(0, 0, 64, 128),
+            ]
+        )
+    }
+
+    #[test]
+    fn padding() {
+        let mut subjects = [
+            RectXYWH::from_wh(10, 10),
+            RectXYWH::from_wh(10, 10),
+            RectXYWH::from_wh(10, 10),
+            RectXYWH::from_wh(20, 10),
+        ];
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                4096,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            )
+            .with_padding(Padding::new(2, 1))
+            .with_rect_padding(vec![0, 0, 0, 3]),
+        )
+        .unwrap()
+        .bin;
+
+        assert_eq!(result, RectWH::new(36, 30));
+        assert_eq!(
+            subjects,
+            [
+                RectXYWH::new(1, 19, 10, 10),
+                RectXYWH::new(13, 19, 10, 10),
+                RectXYWH::new(25, 19, 10, 10),
+                RectXYWH::new(4, 4, 20, 10),
+            ]
+        )
+    }
+
+    #[test]
+    fn bin_size_policy() {
+        let policies = [
+            (BinSizePolicy::PowerOfTwo, RectWH::new(512, 1024)),
+            (BinSizePolicy::SquarePowerOfTwo, RectWH::new(1024, 1024)),
+            (BinSizePolicy::MultipleOf(100), RectWH::new(700, 600)),
+        ];
+
+        for (policy, expected) in policies {
+            let mut subjects = [
+                RectXYWH::from_wh(30, 40),
+                RectXYWH::from_wh(256, 256),
+                RectXYWH::from_wh(128, 512),
+                RectXYWH::from_wh(512, 128),
+            ];
+
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+            let result = find_best_packing(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    4096,
+                    4,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::AbortPacking,
+                )
+                .with_bin_size_policy(policy),
+            )
+            .unwrap()
+            .bin;
+
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn bin_size_policy_limit() {
+        // None of these maximum bins are allowed by the policies, so the search
+        // has to stay within the largest allowed bin that fits into them.
+        let policies = [
+            (BinSizePolicy::Free, 1000, RectWH::new(1000, 1000)),
+            (BinSizePolicy::PowerOfTwo, 1000, RectWH::new(512, 512)),
+            (BinSizePolicy::SquarePowerOfTwo, 1000, RectWH::new(512, 512)),
+            (BinSizePolicy::MultipleOf(256), 700, RectWH::new(512, 512)),
+        ];
+
+        for (policy, max_side, limit) in policies {
+            let mut subjects = [RectXYWH::from_wh(600, 300), RectXYWH::from_wh(300, 300)];
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+            let input = Input::new(
+                max_side,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            )
+            .with_bin_size_policy(policy);
+
+            let result = find_best_packing(&mut root, subjects.iter_mut(), &input).unwrap();
+
+            assert_eq!(validate_packing(&result, &input), []);
+            assert!(result.bin.w <= limit.w && result.bin.h <= limit.h);
+            assert_eq!(policy.round_up(result.bin), result.bin);
+            assert!(result.occupancy <= 1.0);
+
+            // The 600 pixel wide rectangle only fits without a policy.
+            let fits = policy == BinSizePolicy::Free;
+            assert_eq!(result.all_placed(), fits);
+            assert_eq!(result.placements[0].is_some(), fits);
+            assert_eq!(
+                result.statuses[0],
+                if fits {
+                    RectStatus::Placed
+                } else {
+                    RectStatus::Failed
+                }
+            );
+            assert_eq!(result.statuses[1], RectStatus::Placed);
+
+            let packing = find_best_packing_multi(&mut root, subjects.iter_mut(), &input).unwrap();
+
+            for &bin in &packing.bins {
+                assert!(bin.w <= limit.w && bin.h <= limit.h);
+            }
+
+            assert_eq!(packing.pages[0].is_some(), fits);
+        }
+
+        let mut subjects = [RectXYWH::from_wh(10, 10)];
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        assert_eq!(
+            find_best_packing(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    200,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+                .with_bin_size_policy(BinSizePolicy::MultipleOf(256)),
+            ),
+            Err(PackError::InvalidMaxBin)
+        );
+    }
+
+    #[test]
+    fn rectangular_max_bin() {
+        let mut subjects = [
+            RectXYWH::from_wh(1200, 1200),
+            RectXYWH::from_wh(1500, 600),
+            RectXYWH::from_wh(1200, 1200),
+            RectXYWH::from_wh(1200, 1200),
+        ];
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                8192,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            )
+            .with_max_bin(RectWH::new(2048, 8192)),
+        )
+        .unwrap()
+        .bin;
+
+        assert_eq!(result, RectWH::new(1500, 4200));
+        assert_eq!(
+            subjects,
+            [
+                RectXYWH::new(0, 0, 1200, 1200),
+                RectXYWH::new(0, 3600, 1500, 600),
+                RectXYWH::new(0, 1200, 1200, 1200),
+                RectXYWH::new(0, 2400, 1200, 1200),
+            ]
+        )
+    }
+
+    #[test]
+    fn packable() {
+        struct Sprite {
+            size: RectWH,
+        }
+
+        impl Packable for Sprite {
+            fn get_wh(&self) -> RectWH {
+                self.size
+            }
+        }
+
+        let subjects = [
+            Sprite {
+                size: RectWH::new(30, 40),
+            },
+            Sprite {
+                size: RectWH::new(256, 256),
+            },
+            Sprite {
+                size: RectWH::new(0, 10),
+            },
+            Sprite {
+                size: RectWH::new(128, 512),
+            },
+            Sprite {
+                size: RectWH::new(512, 128),
+            },
+        ];
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result: PackingResult<RectXYWH> = pack(
+            &mut root,
+            &subjects,
+            &Input::new(
+                4096,
+                4,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(result.bin, RectWH::new(640, 512));
+        assert_eq!(
+            result.placements,
+            vec![
+                Some(RectXYWH::new(128, 384, 30, 40)),
+                Some(RectXYWH::new(128, 0, 256, 256)),
+                None,
+                Some(RectXYWH::new(0, 0, 128, 512)),
+                Some(RectXYWH::new(128, 256, 512, 128)),
+            ]
+        );
+        assert_eq!(
+            result.statuses,
+            vec![
+                RectStatus::Placed,
+                RectStatus::Placed,
+                RectStatus::Skipped,
+                RectStatus::Placed,
+                RectStatus::Placed,
+            ]
+        );
+        assert!(result.all_placed());
+        assert_eq!(result.used_area, 197808);
+        assert_eq!(result.occupancy, 197808.0 / (640.0 * 512.0));
+        // Sorting by height wins, after 155 trial packings and the final one.
+        assert_eq!(result.best_order, 4);
+        assert_eq!(result.trial_packings, 156);
+
+        // The 512 wide ones don't fit into a 300x300 bin.
+        let result: PackingResult<RectXYWH> = pack(
+            &mut root,
+            &subjects,
+            &Input::new(
+                300,
+                4,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            ),
+        )
+        .unwrap();
+
+        assert!(!result.all_placed());
+        assert_eq!(result.statuses[3], RectStatus::Failed);
+        assert_eq!(result.statuses[4], RectStatus::Failed);
+    }
+
+    #[test]
+    fn unsuccessful_insertion() {
+        // Left over from an earlier packing, which the callback should get to see.
+        let big = RectXYWHF {
+            x: 7,
+            y: 9,
+            w: 200,
+            h: 50,
+            flipped: true,
+        };
+        let mut subjects = [RectXYWHF::from_wh(20, 20), big];
+        let failed = std::cell::RefCell::new(Vec::new());
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+        let input = Input::new(
+            100,
+            1,
+            |_| CallbackResult::ContinuePacking,
+            |r| {
+                failed.borrow_mut().push(r);
+                CallbackResult::ContinuePacking
+            },
+        );
+
+        find_best_packing(&mut root, subjects.iter_mut(), &input).unwrap();
+        assert_eq!(*failed.borrow(), [big]);
+        assert_eq!(subjects[1], big);
+
+        failed.borrow_mut().clear();
+        find_best_packing_multi(&mut root, subjects.iter_mut(), &input).unwrap();
+        assert_eq!(*failed.borrow(), [big]);
+
+        // The slice-based API can only report the size.
+        failed.borrow_mut().clear();
+        let _: PackingResult<RectXYWHF> = pack(
+            &mut root,
+            &[RectWH::new(20, 20), RectWH::new(200, 50)],
+            &input,
+        )
+        .unwrap();
+        assert_eq!(*failed.borrow(), [RectXYWHF::from_wh(200, 50)]);
+    }
+
+    #[test]
+    fn coordinate_types() {
+        fn run<T: Coord>(scale: T, max_bin_side: T) -> (RectWH<T>, Vec<RectXYWH<T>>) {
+            let mut subjects = [(30, 40), (256, 256), (128, 512), (512, 128)]
+                .map(|(w, h)| RectXYWH::from_wh(T::from_i32(w) * scale, T::from_i32(h) * scale));
+
+            let mut root = EmptySpaces::<DefaultEmptySpaces<T>, T>::default();
+
+            let result = find_best_packing(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    max_bin_side,
+                    4,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::AbortPacking,
+                ),
+            )
+            .unwrap()
+            .bin;
+
+            (result, subjects.to_vec())
+        }
+
+        let (bin, rects) = run(1u16, 4096);
+        assert_eq!(bin, RectWH::new(640, 512));
+        assert_eq!(rects[0], RectXYWH::new(128, 384, 30, 40));
+
+        let (bin, rects) = run(0.5f32, 4096.0);
+        assert_eq!(bin, RectWH::new(320.0, 256.0));
+        assert_eq!(rects[0], RectXYWH::new(64.0, 192.0, 15.0, 20.0));
+
+        // Both the bin and the total area are way past what fits in an i32.
+        let (bin, rects) = run(100i64, 1 << 20);
+        assert_eq!(bin, RectWH::new(64000, 51200));
+        assert_eq!(rects[0], RectXYWH::new(12800, 38400, 3000, 4000));
+        assert_eq!(bin.area(), 3_276_800_000);
+
+        assert_eq!(RectWH::new(u32::MAX, u32::MAX).area(), 18446744065119617025);
+    }
+
+    #[test]
+    fn parallel() {
+        let subjects = random_rects(12345, 300, 64);
+
+        let run = |parallel: bool| {
+            let mut rects = subjects.clone();
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+            let bin = find_best_packing(
+                &mut root,
+                rects.iter_mut(),
+                &Input::new(
+                    1024,
+                    -4,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+                .with_padding(Padding::new(1, 0))
+                .with_parallel(parallel),
+            )
+            .unwrap()
+            .bin;
+
+            (bin, rects)
+        };
+
+        assert_eq!(run(true), run(false));
+    }
+
+    #[test]
+    fn atlas() {
+        let mut atlas = Atlas::<DefaultEmptySpaces>::new(RectWH::new(128, 128));
+
+        let quarters: Vec<_> = (0..4).map(|_| atlas.allocate(64, 64).unwrap()).collect();
+
+        assert_eq!(atlas.allocate(1, 1), Err(PackError::DidNotFit));
+        assert_eq!(atlas.len(), 4);
+        assert_eq!(atlas.occupancy(), 1.0);
+
+        let freed = atlas.deallocate(quarters[1]).unwrap().unwrap();
+        assert_eq!(atlas.deallocate(quarters[1]), Ok(None));
+        assert_eq!(atlas.get(quarters[1]), None);
+
+        // The freed quarter is reused, but the stale handle stays invalid.
+        let reused = atlas.allocate(64, 64).unwrap();
+        assert_eq!(atlas.get(reused), Some(freed));
+        assert_eq!(atlas.get(quarters[1]), None);
+
+        for handle in [quarters[0], reused, quarters[2], quarters[3]] {
+            atlas.deallocate(handle).unwrap().unwrap();
+        }
+
+        // Every quarter was merged back into a single space.
+        assert!(atlas.is_empty());
+        assert_eq!(atlas.used_area(), 0);
+        assert_eq!(atlas.get_spaces().get_count(), 1);
+
+        let whole = atlas.allocate(128, 128).unwrap();
+        assert_eq!(atlas.get(whole), Some(RectXYWH::new(0, 0, 128, 128)));
+
+        let mut padded =
+            Atlas::<DefaultEmptySpaces>::new(RectWH::new(64, 64)).with_padding(Padding::new(2, 0));
+
+        let a = padded.allocate(31, 64).unwrap();
+        let b = padded.allocate(31, 64).unwrap();
+        assert_eq!(padded.get(b), Some(RectXYWH::new(33, 0, 31, 64)));
+
+        padded.deallocate(a).unwrap();
+        padded.deallocate(b).unwrap();
+        assert!(padded.allocate(64, 64).is_ok());
+
+        // The left half doesn't share a whole edge with the free bottom right quarter,
+        // so freeing it needs a second space.
+        let mut small = Atlas::<StaticEmptySpaces<1>>::new(RectWH::new(128, 128));
+        let left = small.allocate(64, 128).unwrap();
+        small.allocate(64, 64).unwrap();
+
+        assert_eq!(
+            small.deallocate(left),
+            Err(PackError::ProviderCapacityExceeded)
+        );
+        assert_eq!(small.get(left), None);
+        assert_eq!(small.len(), 1);
+    }
+
+    #[test]
+    fn errors() {
+        let input = Input::new(
+            256,
+            1,
+            |_| CallbackResult::ContinuePacking,
+            |_| CallbackResult::ContinuePacking,
+        );
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let mut empty: [RectXYWH; 0] = [];
+        assert_eq!(
+            find_best_packing(&mut root, empty.iter_mut(), &input),
+            Err(PackError::NoRects)
+        );
+
+        let mut zero_area = [RectXYWH::from_wh(0, 10)];
+        assert_eq!(
+            find_best_packing(&mut root, zero_area.iter_mut(), &input),
+            Err(PackError::NoRects)
+        );
+
+        let mut negative = [RectXYWH::from_wh(10, 10), RectXYWH::from_wh(-1, 10)];
+        assert_eq!(
+            find_best_packing_multi(&mut root, negative.iter_mut(), &input),
+            Err(PackError::InvalidSize { index: 1 })
+        );
+
+        let mut subjects = [RectXYWH::from_wh(10, 10)];
+        assert_eq!(
+            find_best_packing(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    0,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+            ),
+            Err(PackError::InvalidMaxBin)
+        );
+
+        let mut subjects = [RectXYWH::from_wh(10, 10), RectXYWH::from_wh(10, 10)];
+        assert_eq!(
+            find_best_packing(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    256,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+                .with_padding(Padding::new(-5, -3))
+            ),
+            Err(PackError::InvalidPadding)
+        );
+        assert_eq!(
+            find_best_packing_multi(
+                &mut root,
+                subjects.iter_mut(),
+                &Input::new(
+                    256,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+                .with_rect_padding(vec![0, -20])
+            ),
+            Err(PackError::InvalidRectPadding { index: 1 })
+        );
+        // NaN is rejected as well.
+        let mut float = [RectXYWH::from_wh(10.0f32, 10.0)];
+        assert_eq!(
+            find_best_packing(
+                &mut EmptySpaces::<DefaultEmptySpaces<f32>, f32>::default(),
+                float.iter_mut(),
+                &Input::new(
+                    256.0,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::ContinuePacking,
+                )
+                .with_padding(Padding::new(1.0, f32::NAN))
+            ),
+            Err(PackError::InvalidPadding)
+        );
+
+        // Without any orderings, there's nothing to search.
+        let mut subjects = [RectXYWH::from_wh(10, 10)];
+        let no_orderings: [fn(RectXYWH, RectXYWH) -> std::cmp::Ordering; 0] = [];
+        assert_eq!(
+            crate::finders_interface::find_best_packing_ordered(
+                &mut root,
+                subjects.iter_mut(),
+                &input,
+                no_orderings
+            ),
+            Err(PackError::NoOrderings)
+        );
+        assert_eq!(
+            crate::finders_interface::find_best_packing_multi_ordered(
+                &mut root,
+                subjects.iter_mut(),
+                &input,
+                no_orderings
+            ),
+            Err(PackError::NoOrderings)
+        );
+
+        let sizes = [RectWH::new(10, 10)];
+        let no_orderings: [fn(&RectWH, &RectWH) -> std::cmp::Ordering; 0] = [];
+        let result: Result<PackingResult<RectXYWH>, _> = crate::finders_interface::pack_ordered(
+            &mut root,
+            &sizes,
+            &input.with_values(vec![1.0]),
+            no_orderings,
+        );
+        assert_eq!(result, Err(PackError::NoOrderings));
+
+        // Each insertion into a fresh space needs room for two splits,
+        // so a full provider must fail without losing any of its spaces.
+        let mut small = EmptySpaces::<StaticEmptySpaces<2>>::new(RectWH::new(100, 100));
+
+        assert!(small.insert::<RectXYWH>(RectWH::new(10, 20)).is_ok());
+        assert_eq!(small.get_spaces().get_count(), 2);
+        assert_eq!(
+            small.insert::<RectXYWH>(RectWH::new(11, 11)),
+            Err(PackError::ProviderCapacityExceeded)
+        );
+        assert_eq!(small.get_spaces().get_count(), 2);
+
+        // An exact fit doesn't need any more room.
+        assert!(small.insert::<RectXYWH>(RectWH::new(10, 80)).is_ok());
+    }
+
+    #[test]
+    fn max_rects() {
+        let mut root = MaxRects::new(RectWH::new(128, 128));
+
+        for _ in 0..4 {
+            assert!(root.insert::<RectXYWH>(RectWH::new(64, 64)).is_ok());
+        }
+
+        assert_eq!(
+            root.insert::<RectXYWH>(RectWH::new(1, 1)),
+            Err(PackError::DidNotFit)
+        );
+
+        let subjects = random_rects(777, 60, 64);
+
+        for heuristic in [
+            MaxRectsHeuristic::BestShortSideFit,
+            MaxRectsHeuristic::BestLongSideFit,
+            MaxRectsHeuristic::BestAreaFit,
+            MaxRectsHeuristic::BottomLeft,
+            MaxRectsHeuristic::ContactPoint,
+        ] {
+            let mut rects = subjects.clone();
+            let mut root = MaxRects::default().with_heuristic(heuristic);
+
+            let bin = find_best_packing(
+                &mut root,
+                rects.iter_mut(),
+                &Input::new(
+                    1024,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::AbortPacking,
+                )
+                .with_padding(Padding::new(1, 1)),
+            )
+            .unwrap()
+            .bin;
+
+            assert_spaced(&rects, bin);
+        }
+    }
+
+    #[test]
+    fn skyline() {
+        let mut root = Skyline::new(RectWH::new(100, 100));
+
+        let placed = [(60, 20), (40, 50), (30, 30), (70, 10)]
+            .map(|(w, h)| root.insert::<RectXYWH>(RectWH::new(w, h)).unwrap());
+
+        assert_eq!(
+            placed,
+            [
+                RectXYWH::new(0, 0, 60, 20),
+                RectXYWH::new(60, 0, 40, 50),
+                RectXYWH::new(0, 20, 30, 30),
+                RectXYWH::new(0, 50, 70, 10),
+            ]
+        );
+
+        // The gap below the last rectangle can only be used through the waste map.
+        let mut root = Skyline::new(RectWH::new(100, 100)).with_waste_map(true);
+
+        for (w, h) in [(60, 20), (40, 50), (30, 30), (70, 10)] {
+            root.insert::<RectXYWH>(RectWH::new(w, h)).unwrap();
+        }
+
+        assert_eq!(
+            root.insert::<RectXYWH>(RectWH::new(30, 30)),
+            Ok(RectXYWH::new(30, 20, 30, 30))
+        );
+
+        let subjects = random_rects(4242, 100, 64);
+
+        for heuristic in [SkylineHeuristic::BottomLeft, SkylineHeuristic::MinWaste] {
+            for use_waste_map in [false, true] {
+                let mut rects = subjects.clone();
+                let mut root = Skyline::default()
+                    .with_heuristic(heuristic)
+                    .with_waste_map(use_waste_map);
+
+                let bin = find_best_packing(
+                    &mut root,
+                    rects.iter_mut(),
+                    &Input::new(
+                        1024,
+                        1,
+                        |_| CallbackResult::ContinuePacking,
+                        |_| CallbackResult::AbortPacking,
+                    )
+                    .with_padding(Padding::new(1, 1)),
+                )
+                .unwrap()
+                .bin;
+
+                assert_spaced(&rects, bin);
+            }
+        }
+    }
+
+    #[test]
+    fn split_rules() {
+        let spaces_after_insert = |rule| {
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::new(RectWH::new(100, 100));
+            root.set_split_rule(rule);
+            root.insert::<RectXYWH>(RectWH::new(30, 60)).unwrap();
+
+            let spaces = root.get_spaces();
+            (0..spaces.get_count())
+                .map(|i| spaces.get(i))
+                .collect::<Vec<_>>()
+        };
+
+        let right_spans_height = vec![RectXYWH::new(30, 0, 70, 100), RectXYWH::new(0, 60, 30, 40)];
+        let bottom_spans_width = vec![RectXYWH::new(0, 60, 100, 40), RectXYWH::new(30, 0, 70, 60)];
+
+        assert_eq!(
+            spaces_after_insert(SplitRule::LongerLeftoverAxis),
+            right_spans_height
+        );
+        assert_eq!(
+            spaces_after_insert(SplitRule::ShorterLeftoverAxis),
+            bottom_spans_width
+        );
+        assert_eq!(spaces_after_insert(SplitRule::MinArea), right_spans_height);
+        assert_eq!(spaces_after_insert(SplitRule::MaxArea), bottom_spans_width);
+
+        let subjects = random_rects(99, 150, 48);
+
+        let run = |split_rules: Vec<SplitRule>, parallel: bool| {
+            let mut rects = subjects.clone();
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+            let bin = find_best_packing(
+                &mut root,
+                rects.iter_mut(),
+                &Input::new(
+                    1024,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::AbortPacking,
+                )
+                .with_padding(Padding::new(1, 1))
+                .with_split_rules(split_rules)
+                .with_parallel(parallel),
+            )
+            .unwrap()
+            .bin;
+
+            assert_spaced(&rects, bin);
+            (bin, rects, root.get_split_rule())
+        };
+
+        let (default_bin, ..) = run(Vec::new(), false);
+        let searched = run(SplitRule::ALL.to_vec(), false);
+
+        assert!(searched.0.area() <= default_bin.area());
+        assert_eq!(run(SplitRule::ALL.to_vec(), true), searched);
+    }
+
+    #[test]
+    fn space_selection() {
+        let all = [
+            SpaceSelection::ReverseFirstFit,
+            SpaceSelection::BestAreaFit,
+            SpaceSelection::BestShortSideFit,
+            SpaceSelection::BestLongSideFit,
+            SpaceSelection::BottomLeft,
+        ];
+
+        for selection in all {
+            // Leaves a 100x40 space below, and a 40x60 one to the right.
+            let mut root = EmptySpaces::<DefaultEmptySpaces>::new(RectWH::new(100, 100));
+            root.set_selection(selection);
+            root.insert::<RectXYWH>(RectWH::new(60, 60)).unwrap();
+
+            let expected = match selection {
+                SpaceSelection::BestShortSideFit => RectXYWH::new(0, 60, 20, 40),
+                _ => RectXYWH::new(60, 0, 20, 40),
+            };
+
+            assert_eq!(root.insert(RectWH::new(20, 40)), Ok(expected));
+        }
+
+        let subjects = random_rects(31337, 100, 64);
+
+        for selection in all {
+            let run = |parallel: bool| {
+                let mut rects = subjects.clone();
+                let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+                root.set_selection(selection);
+
+                let bin = find_best_packing(
+                    &mut root,
+                    rects.iter_mut(),
+                    &Input::new(
+                        1024,
+                        1,
+                        |_| CallbackResult::ContinuePacking,
+                        |_| CallbackResult::AbortPacking,
+                    )
+                    .with_padding(Padding::new(1, 1))
+                    .with_parallel(parallel),
+                )
+                .unwrap()
+                .bin;
+
+                assert_spaced(&rects, bin);
+                (bin, rects)
+            };
+
+            assert_eq!(run(true), run(false));
+        }
+    }
+
+    #[test]
+    fn reserved() {
+        let reserved = vec![RectXYWH::new(0, 0, 1, 1), RectXYWH::new(448, 448, 64, 64)];
+
+        fn check<P: Packer>(mut root: P, reserved: &[RectXYWH]) {
+            let mut rects = random_rects(4242, 80, 48);
+
+            let input = Input::new(
+                512,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            )
+            .with_reserved(reserved.to_vec());
+
+            let result = find_best_packing(&mut root, rects.iter_mut(), &input).unwrap();
+
+            assert_eq!(result.bin, RectWH::new(512, 512));
+            assert_eq!(validate_packing(&result, &input), []);
+        }
+
+        check(EmptySpaces::<DefaultEmptySpaces>::default(), &reserved);
+        check(MaxRects::default(), &reserved);
+        check(Skyline::default().with_waste_map(true), &reserved);
+
+        // A reserved region cut out of the middle leaves four spaces around it.
+        let mut root = EmptySpaces::<StaticEmptySpaces<3>>::new(RectWH::new(30, 30));
+        assert_eq!(
+            root.reserve(RectXYWH::new(10, 10, 10, 10)),
+            Err(PackError::ProviderCapacityExceeded)
+        );
+        assert_eq!(root.get_spaces().get_count(), 1);
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::new(RectWH::new(30, 30));
+        root.reserve(RectXYWH::new(10, 10, 10, 10)).unwrap();
+        assert_eq!(root.get_spaces().get_count(), 4);
+        assert_eq!(root.get_rects_aabb(), RectWH::new(20, 20));
+
+        let mut rects = [RectXYWH::from_wh(8, 8)];
+        let result = find_best_packing(
+            &mut EmptySpaces::<DefaultEmptySpaces>::default(),
+            rects.iter_mut(),
+            &Input::new(
+                64,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            )
+            .with_reserved(vec![RectXYWH::new(60, 0, 8, 8)]),
+        );
+
+        assert_eq!(result, Err(PackError::InvalidReserved { index: 0 }));
+    }
+
+    #[test]
+    fn reserved_corner() {
+        // There's no room for the edge margin after a region in the bottom right corner.
+        let corner = RectXYWH::new(118, 118, 10, 10);
+
+        fn check<P: Packer>(mut root: P, corner: RectXYWH) {
+            root.set_padding(Padding::new(0, 2));
+            root.reset(RectWH::new(128, 128));
+            root.reserve(corner).unwrap();
+            assert_eq!(root.get_rects_aabb(), RectWH::new(128, 128));
+
+            let mut rects = [RectXYWH::from_wh(10, 10)];
+
+            let input = Input::new(
+                128,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            )
+            .with_padding(Padding::new(0, 2))
+            .with_reserved(vec![corner]);
+
+            let result = find_best_packing(&mut root, rects.iter_mut(), &input).unwrap();
+
+            assert_eq!(result.bin, RectWH::new(128, 128));
+            assert_eq!(result.statuses, [RectStatus::Placed]);
+            assert_eq!(validate_packing(&result, &input), []);
+        }
+
+        check(EmptySpaces::<DefaultEmptySpaces>::default(), corner);
+        check(MaxRects::default(), corner);
+        check(Skyline::default().with_waste_map(true), corner);
+    }
+
+    #[test]
+    fn strip_packing() {
+        let subjects = random_rects(777, 60, 40);
+        let total: i64 = subjects.iter().map(|r| r.area()).sum();
+
+        for mode in [SearchMode::FixedWidth, SearchMode::FixedHeight] {
+            let max_bin = match mode {
+                SearchMode::FixedWidth => RectWH::new(128, 4096),
+                _ => RectWH::new(4096, 128),
+            };
+
+            let mut rects = subjects.clone();
+
+            let bin = find_best_packing(
+                &mut Skyline::default(),
+                rects.iter_mut(),
+                &Input::new(
+                    0,
+                    1,
+                    |_| CallbackResult::ContinuePacking,
+                    |_| CallbackResult::AbortPacking,
+                )
+                .with_max_bin(max_bin)
+                .with_search_mode(mode),
+            )
+            .unwrap()
+            .bin;
+
+            // The pinned side is kept, and the other one is reasonably tight.
+            match mode {
+                SearchMode::FixedWidth => {
+                    assert_eq!(bin.w, 128);
+                    assert!(bin.h < 2 * total as i32 / 128);
+                }
+                _ => {
+                    assert_eq!(bin.h, 128);
+                    assert!(bin.w < 2 * total as i32 / 128);
+                }
+            }
+
+            for r in &rects {
+                assert!(r.x + r.w <= bin.w && r.y + r.h <= bin.h);
+            }
+        }
+    }
+
+    #[test]
+    fn values() {
+        // Either the big one, or the three small ones fit, and the small ones are worth more.
+        let subjects = [
+            RectWH::new(100, 60),
+            RectWH::new(50, 50),
+            RectWH::new(50, 50),
+            RectWH::new(50, 50),
+        ];
+
+        let input = Input::new(
+            100,
+            1,
+            |_| CallbackResult::ContinuePacking,
+            |_| CallbackResult::ContinuePacking,
+        )
+        .with_values(vec![1.0, 5.0, 5.0, 5.0]);
+
+        let result: PackingResult<RectXYWH> = pack(
+            &mut EmptySpaces::<DefaultEmptySpaces>::default(),
+            &subjects,
+            &input,
+        )
+        .unwrap();
+
+        assert!(result.placements[0].is_none());
+        assert!(result.placements[1..].iter().all(Option::is_some));
+
+        // Switching the values around makes the big one win.
+        let input = input.with_values(vec![20.0, 5.0, 5.0, 5.0]);
+        let result: PackingResult<RectXYWH> = pack(
+            &mut EmptySpaces::<DefaultEmptySpaces>::default(),
+            &subjects,
+            &input,
+        )
+        .unwrap();
+
+        assert!(result.placements[0].is_some());
+        assert_eq!(result.placements.iter().flatten().count(), 1);
+
+        let input = input.with_values(vec![f64::NAN]);
+        let result: Result<PackingResult<RectXYWH>, _> = pack(
+            &mut EmptySpaces::<DefaultEmptySpaces>::default(),
+            &subjects,
+            &input,
+        );
+
+        assert_eq!(result, Err(PackError::InvalidValue { index: 0 }));
+    }
+
+    #[test]
+    fn validate() {
+        fn check<P: Packer>(mut root: P, seed: u32) {
+            let mut rects = random_rects(seed, 50, 40);
+            let rect_padding = (0..rects.len()).map(|i| (i % 3) as i32).collect();
+
+            let input = Input::new(
+                1024,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            )
+            .with_padding(Padding::new(2, 1))
+            .with_rect_padding(rect_padding)
+            .with_reserved(vec![
+                RectXYWH::new(0, 0, 1, 1),
+                RectXYWH::new(200, 8, 30, 20),
+            ]);
+
+            let result = find_best_packing(&mut root, rects.iter_mut(), &input).unwrap();
+
+            assert!(result.all_placed());
+            assert_eq!(validate_packing(&result, &input), vec![]);
+        }
+
+        for seed in [1, 2, 3, 99, 1234] {
+            check(EmptySpaces::<DefaultEmptySpaces>::default(), seed);
+            check(MaxRects::default(), seed);
+            check(Skyline::default().with_waste_map(true), seed);
+        }
+
+        let input = Input::new(
+            100,
+            1,
+            |_: RectXYWH| CallbackResult::ContinuePacking,
+            |_: RectXYWH| CallbackResult::ContinuePacking,
+        )
+        .with_padding(Padding::new(2, 0))
+        .with_reserved(vec![RectXYWH::new(90, 90, 10, 10)]);
+
+        let result = PackingResult {
+            bin: RectWH::new(100, 100),
+            placements: vec![
+                Some(RectXYWH::new(0, 0, 10, 10)),
+                Some(RectXYWH::new(5, 5, 10, 10)),
+                Some(RectXYWH::new(0, 11, 10, 10)),
+                Some(RectXYWH::new(95, 0, 10, 10)),
+                None,
+                Some(RectXYWH::new(80, 85, 10, 10)),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validate_packing(&result, &input),
+            vec![
+                Violation::Overlap {
+                    first: 0,
+                    second: 1
+                },
+                Violation::TooClose {
+                    first: 0,
+                    second: 2
+                },
+                Violation::Overlap {
+                    first: 1,
+                    second: 2
+                },
+                Violation::OutsideBin { index: 3 },
+                Violation::Reserved {
+                    index: 5,
+                    reserved: 0
+                },
+            ]
+        );
+
+        let a = RectXYWH::new(0, 0, 10, 10);
+        assert!(a.contains(RectXYWH::new(2, 2, 8, 8)));
+        assert!(!a.intersects(RectXYWH::new(10, 0, 5, 5)));
+        assert_eq!(
+            a.union(RectXYWH::new(5, -5, 20, 5)),
+            RectXYWH::new(0, -5, 25, 15)
+        );
+    }
+
+    #[test]
+    fn svg() {
+        let subjects = [RectWH::new(30, 40), RectWH::new(0, 5), RectWH::new(20, 20)];
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result: PackingResult<RectXYWH> = pack(
+            &mut root,
+            &subjects,
+            &Input::new(
+                64,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            ),
+        )
+        .unwrap();
+
+        let svg = SvgExport::from_result(&result)
+            .with_rect(RectXYWH::new(0, 0, 1, 1), "<white & pixel>")
+            .with_free_spaces(root.get_spaces())
+            .render();
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(&format!(
+            r#"viewBox="0 0 {} {}""#,
+            result.bin.w, result.bin.h
+        )));
+        assert!(svg.contains(">0</text>") && svg.contains(">2</text>"));
+        assert!(!svg.contains(">1</text>"));
+        assert!(svg.contains("&lt;white &amp; pixel&gt;"));
+
+        // The bin, three rectangles and the free spaces.
+        assert_eq!(
+            svg.matches("<rect").count(),
+            4 + root.get_spaces().get_count()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn image_atlas() {
+        use crate::image_atlas::{AtlasBuilder, Image, Sprite, load_sprites};
+
+        // Every pixel is different, so that misplaced or misrotated copies show up.
+        let sprite = |name: &str, width: u32, height: u32| {
+            let mut image = Image::new(width, height);
+
+            for (i, pixel) in image.pixels.chunks_exact_mut(4).enumerate() {
+                pixel.copy_from_slice(&[i as u8, name.len() as u8, 7, 255]);
+            }
+
+            Sprite {
+                name: name.to_owned(),
+                image,
+            }
+        };
+
+        let dir = std::env::temp_dir().join(format!("rectpack2d-atlas-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let originals = [sprite("a", 10, 3), sprite("bb", 3, 10), sprite("ccc", 6, 6)];
+
+        for s in &originals {
+            s.image
+                .save_png(dir.join(format!("{}.png", s.name)))
+                .unwrap();
+        }
+
+        std::fs::write(dir.join("notes.txt"), "not a sprite").unwrap();
+
+        let sprites = load_sprites(&dir).unwrap();
+        assert_eq!(sprites, originals);
+
+        let atlas = AtlasBuilder::new(64)
+            .with_padding(Padding::new(1, 1))
+            .with_allow_flip(true)
+            .build(&sprites)
+            .unwrap();
+
+        for (original, placed) in originals.iter().zip(&atlas.sprites) {
+            assert_eq!(original.name, placed.name);
+
+            for y in 0..original.image.height {
+                for x in 0..original.image.width {
+                    let (ax, ay) = if placed.rotated {
+                        (
+                            placed.rect.x as u32 + original.image.height - 1 - y,
+                            placed.rect.y as u32 + x,
+                        )
+                    } else {
+                        (placed.rect.x as u32 + x, placed.rect.y as u32 + y)
+                    };
+
+                    let from = ((y * original.image.width + x) * 4) as usize;
+                    let to = ((ay * atlas.image.width + ax) * 4) as usize;
+
+                    assert_eq!(
+                        original.image.pixels[from..from + 4],
+                        atlas.image.pixels[to..to + 4]
+                    );
+                }
+            }
+        }
+
+        atlas
+            .save(dir.join("atlas.png"), dir.join("atlas.json"))
+            .unwrap();
+
+        assert_eq!(Image::load_png(dir.join("atlas.png")).unwrap(), atlas.image);
+
+        let manifest = std::fs::read_to_string(dir.join("atlas.json")).unwrap();
+        assert!(manifest.contains(r#""image": "atlas.png""#));
+        assert!(manifest.contains(r#""name": "ccc""#));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            AtlasBuilder::new(8).build(&originals),
+            Err(crate::image_atlas::AtlasError::Pack(PackError::DidNotFit))
+        ));
+
+        // The largest power of two within 1000 is 512, too narrow for the wide sprite.
+        assert!(matches!(
+            AtlasBuilder::new(1000)
+                .with_bin_size_policy(BinSizePolicy::PowerOfTwo)
+                .build(&[sprite("wide", 600, 300), sprite("square", 300, 300)]),
+            Err(crate::image_atlas::AtlasError::Pack(PackError::DidNotFit))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "texturepacker")]
+    fn texture_packer() {
+        use crate::texture_packer::{JsonFormat, TexturePackerAtlas, Trim};
+
+        let mut subjects = [
+            RectXYWHF::from_wh(10, 3),
+            RectXYWHF::from_wh(0, 0),
+            RectXYWHF::from_wh(3, 10),
+        ];
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result: PackingResult<RectXYWHF> = find_best_packing(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                64,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::AbortPacking,
+            ),
+        )
+        .unwrap();
+
+        let atlas =
+            TexturePackerAtlas::from_result("atlas.png", &result, &["a.png", "empty", "b\"c.png"]);
+
+        assert_eq!(atlas.size, result.bin);
+        assert_eq!(atlas.frames.len(), 2);
+        assert!(atlas.frames.iter().any(|f| f.rotated));
+
+        for format in [JsonFormat::Hash, JsonFormat::Array] {
+            let json = atlas.to_json(format);
+            assert_eq!(TexturePackerAtlas::from_json(&json).unwrap(), atlas);
+        }
+
+        // Frame sizes are stored before rotating, and names are kept in order.
+        let json = r#"{
+            "frames": {
+                "z.png": {
+                    "frame": { "x": 2, "y": 4, "w": 10, "h": 20 },
+                    "rotated": true,
+                    "trimmed": true,
+                    "spriteSourceSize": { "x": 1, "y": 3, "w": 10, "h": 20 },
+                    "sourceSize": { "w": 12, "h": 25 }
+                },
+                "a.png": {
+                    "frame": { "x": 30, "y": 0, "w": 5, "h": 5 },
+                    "rotated": false,
+                    "trimmed": false
+                }
+            },
+            "meta": { "image": "sheet.png", "size": { "w": 64, "h": 32 }, "scale": "1" }
+        }"#;
+
+        let read = TexturePackerAtlas::from_json(json).unwrap();
+        assert_eq!(read.image, "sheet.png");
+        assert_eq!(read.size, RectWH::new(64, 32));
+        assert_eq!(read.frames[0].name, "z.png");
+        assert_eq!(read.frames[0].rect, RectXYWH::new(2, 4, 20, 10));
+        assert_eq!(
+            read.frames[0].trim,
+            Some(Trim {
+                x: 1,
+                y: 3,
+                source_size: RectWH::new(12, 25)
+            })
+        );
+        assert_eq!(read.frames[1].trim, None);
+
+        assert_eq!(
+            TexturePackerAtlas::from_json(&read.to_json(JsonFormat::Array)).unwrap(),
+            read
+        );
+        assert!(TexturePackerAtlas::from_json(r#"{ "frames": [{}], "meta": {} }"#).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "libgdx")]
+    fn gdx_atlas() {
+        use crate::gdx_atlas::{GdxAtlas, Page, ParseError, Region};
+
+        let mut subjects = [
+            RectXYWHF::from_wh(60, 20),
+            RectXYWHF::from_wh(0, 0),
+            RectXYWHF::from_wh(50, 50),
+            RectXYWHF::from_wh(20, 60),
+        ];
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let packing = find_best_packing_multi(
+            &mut root,
+            subjects.iter_mut(),
+            &Input::new(
+                64,
+                1,
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            ),
+        )
+        .unwrap();
+
+        let names = ["wide", "empty", "square", "tall"];
+        let atlas = GdxAtlas::from_multi(&packing, &subjects, &names, |page| {
+            format!("atlas{}.png", page + 1)
+        });
+
+        assert_eq!(atlas.pages.len(), packing.bins.len());
+        assert!(atlas.pages.len() > 1);
+        assert_eq!(atlas.pages[1].image, "atlas2.png");
+        assert_eq!(
+            atlas.pages.iter().map(|p| p.regions.len()).sum::<usize>(),
+            3
+        );
+
+        let text = atlas.to_atlas();
+        assert!(text.contains("\nsquare\n  rotate: false\n"));
+        assert_eq!(GdxAtlas::from_atlas(&text).unwrap(), atlas);
+
+        // The format of libGDX 1.9.12 and Spine 4, with the size stored before rotating.
+        let text = "
+skeleton.png
+\tsize: 128, 64
+\tfilter: Linear, Linear
+\tpma: true
+head
+\tbounds: 2, 4, 30, 10
+\toffsets: 1, 2, 32, 14
+\trotate: 90
+eye
+\tbounds: 40, 0, 8, 8
+\tsplit: 1, 2, 3, 4
+
+second.png
+size: 16, 16
+dot
+bounds: 0, 0, 1, 1
+";
+
+        let atlas = GdxAtlas::from_atlas(text).unwrap();
+        assert_eq!(atlas.pages.len(), 2);
+
+        let page = &atlas.pages[0];
+        assert_eq!(page.size, RectWH::new(128, 64));
+        assert_eq!(page.filter, ["Linear", "Linear"]);
+
+        let head = &page.regions[0];
+        assert!(head.rotated);
+        assert_eq!(head.rect, RectXYWH::new(2, 4, 10, 30));
+        assert_eq!((head.offset_x, head.offset_y), (1, 2));
+        assert_eq!(head.original_size, RectWH::new(32, 14));
+
+        let eye = &page.regions[1];
+        assert_eq!(eye.original_size, RectWH::new(8, 8));
+        assert_eq!(eye.split, Some([1, 2, 3, 4]));
+        assert_eq!(eye.index, -1);
+
+        assert_eq!(GdxAtlas::from_atlas(&atlas.to_atlas()).unwrap(), atlas);
+
+        // Region names may contain `:`, which isn't mistaken for an entry.
+        let atlas = GdxAtlas::new().with_page(
+            Page::new("page.png", RectWH::new(16, 16))
+                .with_region(Region::new("hero:idle", RectXYWH::new(0, 0, 8, 8), false))
+                .with_region(Region::new("size: 4", RectXYWH::new(8, 0, 4, 8), true)),
+        );
+        assert_eq!(GdxAtlas::from_atlas(&atlas.to_atlas()).unwrap(), atlas);
+
+        assert_eq!(
+            GdxAtlas::from_atlas("page.png\nsize: 8, 8\nr\n  xy: 0, 0\n"),
+            Err(ParseError::MissingBounds { line: 3 })
+        );
+        assert_eq!(
+            GdxAtlas::from_atlas("page.png\nsize: 8, 8\nr\n  rotate: 45\n"),
+            Err(ParseError::InvalidValue { line: 4 })
+        );
+        assert_eq!(
+            GdxAtlas::from_atlas("\npage.png\nformat: RGBA8888\n"),
+            Err(ParseError::MissingSize { line: 2 })
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "libgdx", feature = "png"))]
+    fn gdx_image_atlas() {
+        use crate::{
+            gdx_atlas::GdxAtlas,
+            image_atlas::{AtlasBuilder, Image, Sprite},
+        };
+
+        let sprite = |name: &str, width: u32, height: u32| {
+            let mut image = Image::new(width, height);
+
+            for (i, pixel) in image.pixels.chunks_exact_mut(4).enumerate() {
+                pixel.copy_from_slice(&[i as u8, name.len() as u8, 7, 255]);
+            }
+
+            Sprite {
+                name: name.to_owned(),
+                image,
+            }
+        };
+
+        // The tall sprite only fits next to the wide one when it's rotated.
+        let sprites = [sprite("wide", 10, 3), sprite("tall", 3, 10)];
+        let atlas = AtlasBuilder::new(10)
+            .with_max_bin(RectWH::new(10, 6))
+            .with_allow_flip(true)
+            .build(&sprites)
+            .unwrap();
+
+        let (gdx, image) = GdxAtlas::from_image_atlas("atlas.png", &atlas);
+        let regions = &gdx.pages[0].regions;
+        assert!(regions.iter().any(|r| r.rotated));
+
+        for ((sprite, region), placed) in sprites.iter().zip(regions).zip(&atlas.sprites) {
+            assert_eq!(region.name, sprite.name);
+            assert_eq!((region.rect, region.rotated), (placed.rect, placed.rotated));
+
+            for y in 0..sprite.image.height {
+                for x in 0..sprite.image.width {
+                    // Rotated regions are stored counter-clockwise.
+                    let (ax, ay) = if region.rotated {
+                        (
+                            region.rect.x as u32 + y,
+                            region.rect.y as u32 + sprite.image.width - 1 - x,
+                        )
+                    } else {
+                        (region.rect.x as u32 + x, region.rect.y as u32 + y)
+                    };
+
+                    let from = ((y * sprite.image.width + x) * 4) as usize;
+                    let to = ((ay * image.width + ax) * 4) as usize;
+
+                    assert_eq!(
+                        sprite.image.pixels[from..from + 4],
+                        image.pixels[to..to + 4]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use crate::finders_interface::InputSettings;
+
+        fn round_trip<V: serde::Serialize + serde::de::DeserializeOwned>(value: &V) -> V {
+            serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+        }
+
+        let settings = Input::new(
+            128,
+            1,
+            |_: RectXYWHF| CallbackResult::ContinuePacking,
+            |_: RectXYWHF| CallbackResult::ContinuePacking,
+        )
+        .with_padding(Padding::new(1, 2))
+        .with_rect_padding(vec![1, 0, 3])
+        .with_bin_size_policy(BinSizePolicy::MultipleOf(4))
+        .with_split_rules(vec![SplitRule::ShorterLeftoverAxis, SplitRule::MinArea])
+        .with_reserved(vec![RectXYWH::new(0, 0, 2, 2)])
+        .with_search_mode(SearchMode::FixedWidth)
+        .with_values(vec![0.1, 2.5, 1.0 / 3.0])
+        .settings();
+
+        assert_eq!(round_trip(&settings), settings);
+
+        let mut subjects = random_rects(7, 20, 24);
+        let mut expected = subjects.clone();
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+
+        let result = find_best_packing(
+            &mut root,
+            subjects.iter_mut(),
+            &round_trip(&settings).into(),
+        )
+        .unwrap();
+        let expected_result = find_best_packing(
+            &mut root,
+            expected.iter_mut(),
+            &settings.clone().into_input(
+                |_| CallbackResult::ContinuePacking,
+                |_| CallbackResult::ContinuePacking,
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(subjects, expected);
+        assert_eq!(result, expected_result);
+        assert_eq!(round_trip(&result), result);
+        assert_eq!(round_trip(&subjects), subjects);
+        assert_eq!(
+            round_trip(&RectWH::new(1.5f32, 2.25)),
+            RectWH::new(1.5, 2.25)
+        );
+
+        let mut root = EmptySpaces::<DefaultEmptySpaces>::default();
+        let multi = find_best_packing_multi(
+            &mut root,
+            subjects.iter_mut(),
+            &InputSettings::new(32, 1).into(),
+        )
+        .unwrap();
+        assert_eq!(round_trip(&multi), multi);
+
+        // Everything but the maximum bin and the discard step can be left out.
+        let minimal: InputSettings =
+            serde_json::from_str(r#"{ "max_bin": { "w": 64, "h": 32 }, "discard_step": 2 }"#)
+                .unwrap();
+        assert_eq!(
+            minimal,
+            InputSettings {
+                max_bin: RectWH::new(64, 32),
+                ..InputSettings::new(0, 2)
+            }
+        );
+    }
 }
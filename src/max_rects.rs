@@ -0,0 +1,349 @@
+use crate::{
+    coord::{self, Coord},
+    empty_spaces::Padding,
+    error::PackError,
+    packer::{Packer, PaddedRect, expand_with_reserved, free_space},
+    rect_structs::{OutputRect, RectWH, RectXYWH, TotalAreaType},
+};
+
+/// How `MaxRects` picks the free rectangle a new one goes into.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum MaxRectsHeuristic {
+    /// Best short side fit: minimizes the shorter leftover side.
+    #[default]
+    BestShortSideFit,
+    /// Best long side fit: minimizes the longer leftover side.
+    BestLongSideFit,
+    /// Best area fit: minimizes the leftover area.
+    BestAreaFit,
+    /// Places rectangles as high up, then as far left as possible.
+    BottomLeft,
+    /// Maximizes the length of the edges touching the bin border or other rectangles.
+    ContactPoint,
+}
+
+/// The configuration of a `MaxRects`, see [`Packer::Settings`].
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct MaxRectsSettings<T: Coord = i32> {
+    pub padding: Padding<T>,
+    pub heuristic: MaxRectsHeuristic,
+}
+
+/// A packer that keeps track of the maximal free rectangles, which unlike
+/// the disjoint spaces of `EmptySpaces` may overlap. This lets rectangles
+/// use space across what would be split lines, at the cost of slower insertion.
+#[derive(Default)]
+pub struct MaxRects<T: Coord = i32> {
+    current_aabb: RectWH<T>,
+    /// The bin given to the last `reset`.
+    bin: RectWH<T>,
+    settings: MaxRectsSettings<T>,
+    /// The initial free space, for finding out what touches the bin border.
+    bounds: RectXYWH<T>,
+    free: Vec<RectXYWH<T>>,
+    /// Inserted rectangles, including their padding.
+    used: Vec<RectXYWH<T>>,
+}
+
+/// Where a padded rectangle would go into a free rectangle, and how the heuristic
+/// rates that. Lower scores are better.
+struct Candidate<T: Coord> {
+    rect: RectXYWH<T>,
+    flipped: bool,
+    score: (TotalAreaType<T>, TotalAreaType<T>),
+}
+
+impl<T: Coord> MaxRects<T> {
+    pub fn new(r: RectWH<T>) -> Self {
+        let mut ret = Self::default();
+        ret.reset(r);
+        ret
+    }
+
+    /// Sets the heuristic used by subsequent insertions.
+    pub fn with_heuristic(mut self, heuristic: MaxRectsHeuristic) -> Self {
+        self.settings.heuristic = heuristic;
+        self
+    }
+
+    pub fn get_heuristic(&self) -> MaxRectsHeuristic {
+        self.settings.heuristic
+    }
+
+    /// Inserts a rectangle into the free space chosen by the heuristic.
+    /// If `R` allows flipping, the rotated orientation is tried as well,
+    /// and is used if it scores strictly better.
+    pub fn insert<R: OutputRect<T>>(&mut self, image_rectangle: RectWH<T>) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, T::ZERO)
+    }
+
+    /// Like `insert`, with `padding` kept free around the rectangle. The free rectangles
+    /// are scored with the padding and the spacing included.
+    pub fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        let padded = PaddedRect::new(image_rectangle, padding, self.settings.padding);
+        let Candidate { rect, flipped, .. } = self
+            .find_best::<R>(padded.size())
+            .ok_or(PackError::DidNotFit)?;
+
+        self.place(rect);
+
+        Ok(padded.place(rect.x, rect.y, flipped, &mut self.current_aabb))
+    }
+
+    /// The spot the heuristic scores best out of the top left corners of the free
+    /// rectangles that `padded` fits into, in either orientation if `R` allows flipping.
+    fn find_best<R: OutputRect<T>>(&self, padded: RectWH<T>) -> Option<Candidate<T>> {
+        let mut best: Option<Candidate<T>> = None;
+
+        for &space in &self.free {
+            let orientations = if R::ALLOW_FLIP {
+                [Some((padded, false)), Some((padded.flipped(), true))]
+            } else {
+                [Some((padded, false)), None]
+            };
+
+            for (wh, flipped) in orientations.into_iter().flatten() {
+                if wh.w > space.w || wh.h > space.h {
+                    continue;
+                }
+
+                let rect = RectXYWH::new(space.x, space.y, wh.w, wh.h);
+                let score = self.score(space, rect);
+
+                if best.as_ref().is_none_or(|b| score < b.score) {
+                    best = Some(Candidate {
+                        rect,
+                        flipped,
+                        score,
+                    });
+                }
+            }
+        }
+
+        best
+    }
+
+    fn score(&self, space: RectXYWH<T>, rect: RectXYWH<T>) -> (TotalAreaType<T>, TotalAreaType<T>) {
+        let leftover_w = (space.w - rect.w).to_area();
+        let leftover_h = (space.h - rect.h).to_area();
+
+        let short_side = if leftover_w < leftover_h {
+            leftover_w
+        } else {
+            leftover_h
+        };
+
+        let long_side = if leftover_w < leftover_h {
+            leftover_h
+        } else {
+            leftover_w
+        };
+
+        match self.settings.heuristic {
+            MaxRectsHeuristic::BestShortSideFit => (short_side, long_side),
+            MaxRectsHeuristic::BestLongSideFit => (long_side, short_side),
+            MaxRectsHeuristic::BestAreaFit => (space.area() - rect.area(), short_side),
+            MaxRectsHeuristic::BottomLeft => ((rect.y + rect.h).to_area(), rect.x.to_area()),
+            // More contact is better, so it is subtracted from the most there can be.
+            MaxRectsHeuristic::ContactPoint => {
+                (rect.perimeter() - self.contact(rect), Default::default())
+            }
+        }
+    }
+
+    /// The length of the edges of `rect` that touch the bin border or inserted rectangles.
+    fn contact(&self, rect: RectXYWH<T>) -> TotalAreaType<T> {
+        let bounds = self.bounds;
+        let mut contact = TotalAreaType::<T>::default();
+
+        if rect.x == bounds.x {
+            contact += rect.h.to_area();
+        }
+
+        if rect.x + rect.w == bounds.x + bounds.w {
+            contact += rect.h.to_area();
+        }
+
+        if rect.y == bounds.y {
+            contact += rect.w.to_area();
+        }
+
+        if rect.y + rect.h == bounds.y + bounds.h {
+            contact += rect.w.to_area();
+        }
+
+        for used in &self.used {
+            if used.x == rect.x + rect.w || used.x + used.w == rect.x {
+                contact +=
+                    common_interval(used.y, used.y + used.h, rect.y, rect.y + rect.h).to_area();
+            }
+
+            if used.y == rect.y + rect.h || used.y + used.h == rect.y {
+                contact +=
+                    common_interval(used.x, used.x + used.w, rect.x, rect.x + rect.w).to_area();
+            }
+        }
+
+        contact
+    }
+
+    /// Cuts `rect` out of every free rectangle, then drops the new pieces
+    /// that are contained in another free rectangle.
+    fn place(&mut self, rect: RectXYWH<T>) {
+        let mut pieces = Vec::new();
+
+        self.free.retain(|&space| {
+            if space.intersects(rect) {
+                split_free_rect(space, rect, &mut pieces);
+                false
+            } else {
+                true
+            }
+        });
+
+        // The untouched free rectangles can't contain each other, and neither can a piece
+        // contain one of them, since it would've been contained in what the piece was cut from.
+        let maximal = pieces
+            .iter()
+            .enumerate()
+            .filter(|&(i, &piece)| {
+                !self.free.iter().any(|&space| space.contains(piece))
+                    && !pieces.iter().enumerate().any(|(j, &other)| {
+                        // Of two identical pieces, only the first one is kept.
+                        j != i && other.contains(piece) && (other != piece || j < i)
+                    })
+            })
+            .map(|(_, &piece)| piece)
+            .collect::<Vec<_>>();
+
+        self.free.extend(maximal);
+        self.used.push(rect);
+    }
+
+    /// Cuts a fixed, pre-placed `region` out of the free space, see `EmptySpaces::reserve`.
+    pub fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        let spacing = self.settings.padding.spacing;
+
+        self.place(RectXYWH::new(
+            region.x,
+            region.y,
+            region.w + spacing,
+            region.h + spacing,
+        ));
+
+        expand_with_reserved(
+            &mut self.current_aabb,
+            region,
+            self.settings.padding.edge_margin,
+            self.bin,
+        );
+
+        Ok(())
+    }
+
+    pub fn get_rects_aabb(&self) -> RectWH<T> {
+        self.current_aabb
+    }
+
+    /// The maximal free rectangles, which may overlap.
+    pub fn get_free_rects(&self) -> &[RectXYWH<T>] {
+        &self.free
+    }
+}
+
+impl<T: Coord> Packer<T> for MaxRects<T> {
+    type Settings = MaxRectsSettings<T>;
+
+    fn settings(&self) -> Self::Settings {
+        self.settings
+    }
+
+    fn from_settings(settings: Self::Settings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    fn set_padding(&mut self, padding: Padding<T>) {
+        self.settings.padding = padding;
+    }
+
+    fn get_padding(&self) -> Padding<T> {
+        self.settings.padding
+    }
+
+    /// Makes the free space of `bin` the only free rectangle, and forgets what was inserted.
+    fn reset(&mut self, bin: RectWH<T>) {
+        self.current_aabb = Default::default();
+        self.bin = bin;
+        self.bounds = free_space(bin, self.settings.padding);
+
+        self.free.clear();
+        self.free.push(self.bounds);
+        self.used.clear();
+    }
+
+    fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, padding)
+    }
+
+    fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        self.reserve(region)
+    }
+
+    fn get_rects_aabb(&self) -> RectWH<T> {
+        self.get_rects_aabb()
+    }
+}
+
+/// The length of the overlap of the `[a_start, a_end]` and `[b_start, b_end]` intervals.
+fn common_interval<T: Coord>(a_start: T, a_end: T, b_start: T, b_end: T) -> T {
+    if a_end < b_start || b_end < a_start {
+        T::ZERO
+    } else {
+        coord::min(a_end, b_end) - coord::max(a_start, b_start)
+    }
+}
+
+/// Pushes the up to four maximal rectangles that are left of `space` after cutting `used` out of it.
+fn split_free_rect<T: Coord>(space: RectXYWH<T>, used: RectXYWH<T>, out: &mut Vec<RectXYWH<T>>) {
+    let space_right = space.x + space.w;
+    let space_bottom = space.y + space.h;
+    let used_right = used.x + used.w;
+    let used_bottom = used.y + used.h;
+
+    if used.y > space.y {
+        out.push(RectXYWH::new(space.x, space.y, space.w, used.y - space.y));
+    }
+
+    if used_bottom < space_bottom {
+        out.push(RectXYWH::new(
+            space.x,
+            used_bottom,
+            space.w,
+            space_bottom - used_bottom,
+        ));
+    }
+
+    if used.x > space.x {
+        out.push(RectXYWH::new(space.x, space.y, used.x - space.x, space.h));
+    }
+
+    if used_right < space_right {
+        out.push(RectXYWH::new(
+            used_right,
+            space.y,
+            space_right - used_right,
+            space.h,
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    coord::{self, Coord},
+    empty_spaces::{EmptySpaces, EmptySpacesProviderTrait, EmptySpacesSettings, Padding},
+    error::PackError,
+    insert_and_split::SplitRule,
+    rect_structs::{OutputRect, RectWH, RectXYWH},
+};
+
+/// Algorithms that place rectangles into a bin one by one.
+/// The best bin search works with any of them, so
+/// `EmptySpaces` can be swapped for e.g. `MaxRects`.
+pub trait Packer<T: Coord = i32> {
+    /// Everything that configures the packer, including its padding.
+    /// The parallel search uses it to create an identical packer for every thread.
+    type Settings: Copy + Send + Sync;
+
+    fn settings(&self) -> Self::Settings;
+
+    /// Creates a packer with the given settings. It has to be `reset` before inserting.
+    fn from_settings(settings: Self::Settings) -> Self;
+
+    /// Sets the padding used by subsequent calls to `reset` and `insert_padded`.
+    fn set_padding(&mut self, padding: Padding<T>);
+
+    fn get_padding(&self) -> Padding<T>;
+
+    /// Sets the rule for splitting free spaces, for packers that split them the way
+    /// `insert_and_split` does. The others ignore it.
+    fn set_split_rule(&mut self, _split_rule: SplitRule) {}
+
+    /// Empties the packer, making `bin` the only free space.
+    fn reset(&mut self, bin: RectWH<T>);
+
+    /// Inserts a rectangle, keeping `padding` free on every side of it
+    /// on top of the spacing. The returned rectangle doesn't include any of the padding.
+    fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError>;
+
+    /// Cuts a fixed, pre-placed `region` out of the free space until the next `reset`.
+    fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError>;
+
+    /// The bounding box of everything inserted since the last `reset`.
+    fn get_rects_aabb(&self) -> RectWH<T>;
+}
+
+/// The free space of an empty `bin`. The edge margin is cut off of it, and the spacing is
+/// added back, since the last rectangle in a row doesn't need any space after itself.
+pub(crate) fn free_space<T: Coord>(bin: RectWH<T>, padding: Padding<T>) -> RectXYWH<T> {
+    let Padding {
+        spacing,
+        edge_margin,
+    } = padding;
+
+    RectXYWH::new(
+        edge_margin,
+        edge_margin,
+        coord::sub_or_zero(bin.w + spacing, T::TWO * edge_margin),
+        coord::sub_or_zero(bin.h + spacing, T::TWO * edge_margin),
+    )
+}
+
+/// Grows `aabb` by a reserved `region` and the edge margin after it. The region may touch
+/// the border of `bin`, where there's no room for the margin, so `aabb` stays within `bin`.
+pub(crate) fn expand_with_reserved<T: Coord>(
+    aabb: &mut RectWH<T>,
+    region: RectXYWH<T>,
+    edge_margin: T,
+    bin: RectWH<T>,
+) {
+    aabb.expand_with_padded(region, edge_margin);
+    aabb.w = coord::max(coord::min(aabb.w, bin.w), region.x + region.w);
+    aabb.h = coord::max(coord::min(aabb.h, bin.h), region.y + region.h);
+}
+
+/// A rectangle passed to `Packer::insert_padded`, along with the padding it needs.
+/// Packers only have to find room for its `size`, and hand the spot over to `place`.
+pub(crate) struct PaddedRect<T: Coord> {
+    image_rectangle: RectWH<T>,
+    padding: T,
+    settings: Padding<T>,
+}
+
+impl<T: Coord> PaddedRect<T> {
+    pub(crate) fn new(image_rectangle: RectWH<T>, padding: T, settings: Padding<T>) -> Self {
+        Self {
+            image_rectangle,
+            padding,
+            settings,
+        }
+    }
+
+    /// The room it takes up in the free space: the padding on every side, and the spacing.
+    pub(crate) fn size(&self) -> RectWH<T> {
+        let inflation = T::TWO * self.padding + self.settings.spacing;
+
+        RectWH::new(
+            self.image_rectangle.w + inflation,
+            self.image_rectangle.h + inflation,
+        )
+    }
+
+    /// The rectangle itself, given that its room was found at `x` and `y`, flipped or not.
+    /// `aabb` is grown to include it, along with its padding and the edge margin.
+    pub(crate) fn place<R: OutputRect<T>>(
+        &self,
+        x: T,
+        y: T,
+        flipped: bool,
+        aabb: &mut RectWH<T>,
+    ) -> R {
+        let RectWH { w, h } = self.image_rectangle;
+        let (x, y) = (x + self.padding, y + self.padding);
+        let placed = if flipped { (h, w) } else { (w, h) };
+
+        aabb.expand_with_padded(
+            RectXYWH::new(x, y, placed.0, placed.1),
+            self.padding + self.settings.edge_margin,
+        );
+
+        R::placed(x, y, w, h, flipped)
+    }
+}
+
+impl<EmptySpacesProvider: EmptySpacesProviderTrait<T>, T: Coord> Packer<T>
+    for EmptySpaces<EmptySpacesProvider, T>
+{
+    type Settings = EmptySpacesSettings<T>;
+
+    fn settings(&self) -> Self::Settings {
+        self.get_settings()
+    }
+
+    fn from_settings(settings: Self::Settings) -> Self {
+        let mut ret = Self::default();
+        ret.set_settings(settings);
+        ret
+    }
+
+    fn set_padding(&mut self, padding: Padding<T>) {
+        self.set_padding(padding)
+    }
+
+    fn get_padding(&self) -> Padding<T> {
+        self.get_padding()
+    }
+
+    fn set_split_rule(&mut self, split_rule: SplitRule) {
+        self.set_split_rule(split_rule)
+    }
+
+    fn reset(&mut self, bin: RectWH<T>) {
+        self.reset(bin)
+    }
+
+    fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, padding)
+    }
+
+    fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        self.reserve(region)
+    }
+
+    fn get_rects_aabb(&self) -> RectWH<T> {
+        self.get_rects_aabb()
+    }
+}
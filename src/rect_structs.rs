@@ -1,72 +1,245 @@
-pub type TotalAreaType = i32;
+use crate::coord::{self, Coord};
+
+pub type TotalAreaType<T = i32> = <T as Coord>::Area;
+
+/// Types that can be packed, i.e. have a size.
+pub trait Packable<T: Coord = i32> {
+    /// The dimensions of the rectangle, as it would be inserted.
+    fn get_wh(&self) -> RectWH<T>;
+}
+
+/// Rectangle types the packer can write its results into.
+pub trait OutputRect<T: Coord = i32>: Packable<T> + Copy {
+    /// Whether the packer may rotate rectangles of this type by 90 degrees.
+    const ALLOW_FLIP: bool;
+
+    /// Creates a rectangle placed at `x` and `y`. `w` and `h` are
+    /// the dimensions before flipping.
+    fn placed(x: T, y: T, w: T, h: T, flipped: bool) -> Self;
+
+    /// The area the rectangle covers, as placed.
+    fn get_xywh(&self) -> RectXYWH<T>;
+
+    /// Whether the packer rotated the rectangle by 90 degrees.
+    fn flipped(&self) -> bool {
+        false
+    }
+}
 
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
-pub struct RectWH {
-    pub w: i32,
-    pub h: i32,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RectWH<T: Coord = i32> {
+    pub w: T,
+    pub h: T,
 }
 
-impl RectWH {
-    pub fn new(w: i32, h: i32) -> Self {
+impl<T: Coord> RectWH<T> {
+    pub fn new(w: T, h: T) -> Self {
         Self { w, h }
     }
 
-    pub fn max_side(&self) -> i32 {
-        self.w.max(self.h)
+    pub fn max_side(&self) -> T {
+        coord::max(self.w, self.h)
     }
 
-    pub fn min_side(&self) -> i32 {
-        self.w.min(self.h)
+    pub fn min_side(&self) -> T {
+        coord::min(self.w, self.h)
     }
 
-    pub fn area(&self) -> i32 {
-        self.w * self.h
+    pub fn area(&self) -> T::Area {
+        self.w.to_area() * self.h.to_area()
     }
 
-    pub fn perimeter(&self) -> i32 {
-        2 * self.w + 2 * self.h
+    pub fn perimeter(&self) -> T::Area {
+        let two = T::TWO.to_area();
+
+        two * self.w.to_area() + two * self.h.to_area()
+    }
+
+    /// Returns the rectangle rotated by 90 degrees.
+    pub fn flipped(&self) -> Self {
+        Self::new(self.h, self.w)
+    }
+
+    pub fn expand_with(&mut self, r: RectXYWH<T>) {
+        self.expand_with_padded(r, T::ZERO);
+    }
+
+    /// Like `expand_with`, but also includes `padding` to the right of and below `r`.
+    pub fn expand_with_padded(&mut self, r: RectXYWH<T>, padding: T) {
+        self.w = coord::max(self.w, r.x + r.w + padding);
+        self.h = coord::max(self.h, r.y + r.h + padding);
     }
+}
 
-    pub fn expand_with(&mut self, r: RectXYWH) {
-        self.w = self.w.max(r.x + r.w);
-        self.h = self.h.max(r.y + r.h);
+impl<T: Coord> Packable<T> for RectWH<T> {
+    fn get_wh(&self) -> RectWH<T> {
+        *self
     }
 }
 
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
-pub struct RectXYWH {
-    pub x: i32,
-    pub y: i32,
-    pub w: i32,
-    pub h: i32,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RectXYWH<T: Coord = i32> {
+    pub x: T,
+    pub y: T,
+    pub w: T,
+    pub h: T,
 }
 
-impl RectXYWH {
-    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+impl<T: Coord> RectXYWH<T> {
+    pub fn new(x: T, y: T, w: T, h: T) -> Self {
         Self { x, y, w, h }
     }
 
-    pub fn from_wh(w: i32, h: i32) -> Self {
-        Self::new(0, 0, w, h)
+    pub fn from_wh(w: T, h: T) -> Self {
+        Self::new(T::ZERO, T::ZERO, w, h)
+    }
+
+    pub fn area(&self) -> T::Area {
+        RectWH::from(self).area()
+    }
+
+    pub fn perimeter(&self) -> T::Area {
+        RectWH::from(self).perimeter()
+    }
+
+    /// Whether the two rectangles overlap. Rectangles that only touch don't.
+    pub fn intersects(&self, other: Self) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+
+    /// Whether all of `other` is inside of this rectangle.
+    pub fn contains(&self, other: Self) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.w <= self.x + self.w
+            && other.y + other.h <= self.y + self.h
+    }
+
+    /// The smallest rectangle that contains both rectangles.
+    pub fn union(&self, other: Self) -> Self {
+        let x = coord::min(self.x, other.x);
+        let y = coord::min(self.y, other.y);
+
+        Self::new(
+            x,
+            y,
+            coord::max(self.x + self.w, other.x + other.w) - x,
+            coord::max(self.y + self.h, other.y + other.h) - y,
+        )
+    }
+}
+
+impl<T: Coord> OutputRect<T> for RectXYWH<T> {
+    const ALLOW_FLIP: bool = false;
+
+    fn placed(x: T, y: T, w: T, h: T, _flipped: bool) -> Self {
+        Self::new(x, y, w, h)
+    }
+
+    fn get_xywh(&self) -> RectXYWH<T> {
+        *self
     }
+}
+
+impl<T: Coord> Packable<T> for RectXYWH<T> {
+    fn get_wh(&self) -> RectWH<T> {
+        self.into()
+    }
+}
+
+/// A rectangle that the packer is allowed to rotate by 90 degrees.
+/// If `flipped` is set, `w` and `h` describe the rectangle as placed,
+/// i.e. they are swapped relative to the original image.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RectXYWHF<T: Coord = i32> {
+    pub x: T,
+    pub y: T,
+    pub w: T,
+    pub h: T,
+    pub flipped: bool,
+}
+
+impl<T: Coord> RectXYWHF<T> {
+    /// Swaps `w` and `h` if `flipped` is set.
+    pub fn new(x: T, y: T, w: T, h: T, flipped: bool) -> Self {
+        let (w, h) = if flipped { (h, w) } else { (w, h) };
+
+        Self {
+            x,
+            y,
+            w,
+            h,
+            flipped,
+        }
+    }
+
+    pub fn from_wh(w: T, h: T) -> Self {
+        Self::new(T::ZERO, T::ZERO, w, h, false)
+    }
+
+    pub fn area(&self) -> T::Area {
+        RectWH::from(self).area()
+    }
+
+    pub fn perimeter(&self) -> T::Area {
+        RectWH::from(self).perimeter()
+    }
+}
 
-    pub fn area(&self) -> i32 {
-        self.w * self.h
+impl<T: Coord> OutputRect<T> for RectXYWHF<T> {
+    const ALLOW_FLIP: bool = true;
+
+    fn placed(x: T, y: T, w: T, h: T, flipped: bool) -> Self {
+        Self::new(x, y, w, h, flipped)
+    }
+
+    fn get_xywh(&self) -> RectXYWH<T> {
+        RectXYWH::new(self.x, self.y, self.w, self.h)
+    }
+
+    fn flipped(&self) -> bool {
+        self.flipped
     }
+}
+
+impl<T: Coord> Packable<T> for RectXYWHF<T> {
+    fn get_wh(&self) -> RectWH<T> {
+        self.into()
+    }
+}
 
-    pub fn perimeter(&self) -> i32 {
-        2 * self.w + 2 * self.h
+impl<T: Coord> From<RectXYWH<T>> for RectXYWHF<T> {
+    fn from(value: RectXYWH<T>) -> Self {
+        Self::new(value.x, value.y, value.w, value.h, false)
+    }
+}
+
+impl<T: Coord> From<&RectXYWH<T>> for RectWH<T> {
+    fn from(value: &RectXYWH<T>) -> Self {
+        Self::new(value.w, value.h)
+    }
+}
+
+impl<T: Coord> From<&mut RectXYWH<T>> for RectWH<T> {
+    fn from(value: &mut RectXYWH<T>) -> Self {
+        Self::new(value.w, value.h)
     }
 }
 
-impl From<&RectXYWH> for RectWH {
-    fn from(value: &RectXYWH) -> Self {
+impl<T: Coord> From<&RectXYWHF<T>> for RectWH<T> {
+    fn from(value: &RectXYWHF<T>) -> Self {
         Self::new(value.w, value.h)
     }
 }
 
-impl From<&mut RectXYWH> for RectWH {
-    fn from(value: &mut RectXYWH) -> Self {
+impl<T: Coord> From<&mut RectXYWHF<T>> for RectWH<T> {
+    fn from(value: &mut RectXYWHF<T>) -> Self {
         Self::new(value.w, value.h)
     }
 }
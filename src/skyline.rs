@@ -0,0 +1,423 @@
+use crate::{
+    coord::{self, Coord},
+    empty_spaces::Padding,
+    error::PackError,
+    insert_and_split::{SplitRule, cut_out, insert_and_split},
+    packer::{Packer, PaddedRect, expand_with_reserved, free_space},
+    rect_structs::{OutputRect, RectWH, RectXYWH, TotalAreaType},
+};
+
+/// How `Skyline` picks where a new rectangle goes.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum SkylineHeuristic {
+    /// Places rectangles as high up, then as far left as possible.
+    #[default]
+    BottomLeft,
+    /// Minimizes the area left unusable below the rectangle,
+    /// then places it as high up as possible.
+    MinWaste,
+}
+
+/// The configuration of a `Skyline`, see [`Packer::Settings`].
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct SkylineSettings<T: Coord = i32> {
+    pub padding: Padding<T>,
+    pub heuristic: SkylineHeuristic,
+    /// Keep track of the space left below the skyline, and try it first.
+    pub use_waste_map: bool,
+    /// How the spaces in the waste map are split.
+    pub split_rule: SplitRule,
+}
+
+/// A horizontal piece of the skyline, `w` wide and at height `y`.
+#[derive(Clone, Copy, Debug)]
+struct Segment<T: Coord> {
+    x: T,
+    y: T,
+    w: T,
+}
+
+/// A packer that only tracks the top edge of what was placed so far, which makes
+/// inserting fast enough for online use, such as glyph caches. Space left below
+/// the skyline is lost, unless the waste map is enabled.
+#[derive(Default)]
+pub struct Skyline<T: Coord = i32> {
+    current_aabb: RectWH<T>,
+    /// The bin given to the last `reset`.
+    bin: RectWH<T>,
+    settings: SkylineSettings<T>,
+    /// The initial free space.
+    bounds: RectXYWH<T>,
+    skyline: Vec<Segment<T>>,
+    /// Free spaces below the skyline, split the same way `EmptySpaces` does it.
+    waste: Vec<RectXYWH<T>>,
+}
+
+/// A spot on top of the skyline, starting at segment `index`,
+/// and how the heuristic rates it. Lower scores are better.
+struct Candidate<T: Coord> {
+    index: usize,
+    rect: RectXYWH<T>,
+    flipped: bool,
+    score: (TotalAreaType<T>, TotalAreaType<T>),
+}
+
+impl<T: Coord> Skyline<T> {
+    pub fn new(r: RectWH<T>) -> Self {
+        let mut ret = Self::default();
+        ret.reset(r);
+        ret
+    }
+
+    /// Sets the heuristic used by subsequent insertions.
+    pub fn with_heuristic(mut self, heuristic: SkylineHeuristic) -> Self {
+        self.settings.heuristic = heuristic;
+        self
+    }
+
+    /// Enables or disables the waste map for subsequent insertions.
+    pub fn with_waste_map(mut self, use_waste_map: bool) -> Self {
+        self.settings.use_waste_map = use_waste_map;
+        self
+    }
+
+    /// Inserts a rectangle on top of the skyline, or into the waste map if it's enabled
+    /// and there's room in it. If `R` allows flipping, the rotated orientation
+    /// is tried as well, and is used if it scores strictly better.
+    pub fn insert<R: OutputRect<T>>(&mut self, image_rectangle: RectWH<T>) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, T::ZERO)
+    }
+
+    /// Like `insert`, with `padding` kept free around the rectangle. The padding
+    /// and the spacing raise the skyline along with the rectangle itself.
+    pub fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        let padded = PaddedRect::new(image_rectangle, padding, self.settings.padding);
+
+        let (rect, flipped) = match self.insert_into_waste::<R>(padded.size()) {
+            Some(found) => found,
+            None => {
+                let best = self
+                    .find_on_skyline::<R>(padded.size())
+                    .ok_or(PackError::DidNotFit)?;
+                self.place_on_skyline(best.index, best.rect);
+
+                (best.rect, best.flipped)
+            }
+        };
+
+        Ok(padded.place(rect.x, rect.y, flipped, &mut self.current_aabb))
+    }
+
+    /// Takes the first waste space that fits, the same way `EmptySpaces` does it.
+    fn insert_into_waste<R: OutputRect<T>>(
+        &mut self,
+        wh: RectWH<T>,
+    ) -> Option<(RectXYWH<T>, bool)> {
+        if !self.settings.use_waste_map {
+            return None;
+        }
+
+        for i in (0..self.waste.len()).rev() {
+            let space = self.waste[i];
+
+            for (wh, flipped) in [(wh, false), (wh.flipped(), true)] {
+                if flipped && !R::ALLOW_FLIP {
+                    continue;
+                }
+
+                let splits = insert_and_split(wh, space, self.settings.split_rule);
+
+                if splits.is_valid() {
+                    self.waste.swap_remove(i);
+                    self.waste
+                        .extend_from_slice(&splits.spaces[..splits.count as usize]);
+
+                    return Some((RectXYWH::new(space.x, space.y, wh.w, wh.h), flipped));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_on_skyline<R: OutputRect<T>>(&self, wh: RectWH<T>) -> Option<Candidate<T>> {
+        let mut best: Option<Candidate<T>> = None;
+
+        for index in 0..self.skyline.len() {
+            for (wh, flipped) in [(wh, false), (wh.flipped(), true)] {
+                if flipped && !R::ALLOW_FLIP {
+                    continue;
+                }
+
+                let Some(y) = self.fit(index, wh) else {
+                    continue;
+                };
+
+                let rect = RectXYWH::new(self.skyline[index].x, y, wh.w, wh.h);
+                let top = (y + wh.h).to_area();
+
+                let score = match self.settings.heuristic {
+                    SkylineHeuristic::BottomLeft => (top, rect.x.to_area()),
+                    SkylineHeuristic::MinWaste => (self.waste_below(index, rect), top),
+                };
+
+                if best.as_ref().is_none_or(|b| score < b.score) {
+                    best = Some(Candidate {
+                        index,
+                        rect,
+                        flipped,
+                        score,
+                    });
+                }
+            }
+        }
+
+        best
+    }
+
+    /// The lowest `y` a rectangle starting at segment `index` can be placed at, if any.
+    fn fit(&self, index: usize, wh: RectWH<T>) -> Option<T> {
+        let x = self.skyline[index].x;
+
+        if x + wh.w > self.bounds.x + self.bounds.w {
+            return None;
+        }
+
+        let mut y = self.skyline[index].y;
+
+        for segment in self.covered(index, x + wh.w) {
+            y = coord::max(y, segment.y);
+
+            if y + wh.h > self.bounds.y + self.bounds.h {
+                return None;
+            }
+        }
+
+        Some(y)
+    }
+
+    /// The segments from `index` on, up to the one that contains `right`.
+    fn covered(&self, index: usize, right: T) -> impl Iterator<Item = &Segment<T>> {
+        self.skyline[index..]
+            .iter()
+            .take_while(move |segment| segment.x < right)
+    }
+
+    /// The area between the skyline and the bottom of `rect`.
+    fn waste_below(&self, index: usize, rect: RectXYWH<T>) -> TotalAreaType<T> {
+        let mut waste = TotalAreaType::<T>::default();
+
+        for segment in self.covered(index, rect.x + rect.w) {
+            let overlap = coord::min(segment.x + segment.w, rect.x + rect.w) - segment.x;
+            waste += overlap.to_area() * (rect.y - segment.y).to_area();
+        }
+
+        waste
+    }
+
+    /// Raises the skyline to the bottom of `rect`, which starts at segment `index`.
+    fn place_on_skyline(&mut self, index: usize, rect: RectXYWH<T>) {
+        let right = rect.x + rect.w;
+
+        if self.settings.use_waste_map {
+            let wasted = self
+                .covered(index, right)
+                .filter(|segment| segment.y < rect.y)
+                .map(|segment| {
+                    let w = coord::min(segment.x + segment.w, right) - segment.x;
+                    RectXYWH::new(segment.x, segment.y, w, rect.y - segment.y)
+                })
+                .collect::<Vec<_>>();
+
+            self.waste.extend(wasted);
+        }
+
+        let covered = self.covered(index, right).count();
+        let last = self.skyline[index + covered - 1];
+        let last_right = last.x + last.w;
+
+        let mut replacement = vec![Segment {
+            x: rect.x,
+            y: rect.y + rect.h,
+            w: rect.w,
+        }];
+
+        // Whatever of the last covered segment sticks out to the right stays as it was.
+        if last_right > right {
+            replacement.push(Segment {
+                x: right,
+                y: last.y,
+                w: last_right - right,
+            });
+        }
+
+        self.skyline.splice(index..index + covered, replacement);
+
+        self.merge_skyline();
+    }
+
+    /// Cuts a fixed, pre-placed `region` out of the free space, see `EmptySpaces::reserve`.
+    /// The skyline can't go around it, so it's raised to the bottom of the region,
+    /// and the space above the region goes to the waste map, if it's enabled.
+    pub fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        let spacing = self.settings.padding.spacing;
+        let cut = RectXYWH::new(region.x, region.y, region.w + spacing, region.h + spacing);
+
+        let mut pieces = Vec::new();
+
+        self.waste.retain(|&space| {
+            if space.intersects(cut) {
+                cut_out(space, cut, &mut pieces);
+                false
+            } else {
+                true
+            }
+        });
+
+        self.waste.extend(pieces);
+
+        let left = coord::max(cut.x, self.bounds.x);
+        let right = coord::min(cut.x + cut.w, self.bounds.x + self.bounds.w);
+
+        if left < right {
+            self.split_skyline_at(left);
+            self.split_skyline_at(right);
+
+            for segment in &mut self.skyline {
+                if segment.x < left || segment.x >= right {
+                    continue;
+                }
+
+                if self.settings.use_waste_map && segment.y < cut.y {
+                    self.waste.push(RectXYWH::new(
+                        segment.x,
+                        segment.y,
+                        segment.w,
+                        cut.y - segment.y,
+                    ));
+                }
+
+                segment.y = coord::max(segment.y, cut.y + cut.h);
+            }
+
+            self.merge_skyline();
+        }
+
+        expand_with_reserved(
+            &mut self.current_aabb,
+            region,
+            self.settings.padding.edge_margin,
+            self.bin,
+        );
+
+        Ok(())
+    }
+
+    /// Splits the segment that `x` falls inside of in two, so that one starts at `x`.
+    fn split_skyline_at(&mut self, x: T) {
+        let Some(i) = self
+            .skyline
+            .iter()
+            .position(|segment| segment.x < x && x < segment.x + segment.w)
+        else {
+            return;
+        };
+
+        let segment = self.skyline[i];
+        self.skyline[i].w = x - segment.x;
+        self.skyline.insert(
+            i + 1,
+            Segment {
+                x,
+                y: segment.y,
+                w: segment.x + segment.w - x,
+            },
+        );
+    }
+
+    /// Joins neighbouring segments of the same height.
+    fn merge_skyline(&mut self) {
+        let mut i = 1;
+
+        while i < self.skyline.len() {
+            if self.skyline[i - 1].y == self.skyline[i].y {
+                let w = self.skyline[i].w;
+                self.skyline[i - 1].w += w;
+                self.skyline.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    pub fn get_rects_aabb(&self) -> RectWH<T> {
+        self.current_aabb
+    }
+
+    /// The free spaces below the skyline, if the waste map is enabled.
+    pub fn get_waste_rects(&self) -> &[RectXYWH<T>] {
+        &self.waste
+    }
+}
+
+impl<T: Coord> Packer<T> for Skyline<T> {
+    type Settings = SkylineSettings<T>;
+
+    fn settings(&self) -> Self::Settings {
+        self.settings
+    }
+
+    fn from_settings(settings: Self::Settings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    fn set_padding(&mut self, padding: Padding<T>) {
+        self.settings.padding = padding;
+    }
+
+    fn get_padding(&self) -> Padding<T> {
+        self.settings.padding
+    }
+
+    fn set_split_rule(&mut self, split_rule: SplitRule) {
+        self.settings.split_rule = split_rule;
+    }
+
+    /// Flattens the skyline to the bottom of the free space of `bin`, and empties the waste map.
+    fn reset(&mut self, bin: RectWH<T>) {
+        self.current_aabb = Default::default();
+        self.bin = bin;
+        self.bounds = free_space(bin, self.settings.padding);
+
+        self.skyline.clear();
+        self.skyline.push(Segment {
+            x: self.bounds.x,
+            y: self.bounds.y,
+            w: self.bounds.w,
+        });
+        self.waste.clear();
+    }
+
+    fn insert_padded<R: OutputRect<T>>(
+        &mut self,
+        image_rectangle: RectWH<T>,
+        padding: T,
+    ) -> Result<R, PackError> {
+        self.insert_padded(image_rectangle, padding)
+    }
+
+    fn reserve(&mut self, region: RectXYWH<T>) -> Result<(), PackError> {
+        self.reserve(region)
+    }
+
+    fn get_rects_aabb(&self) -> RectWH<T> {
+        self.get_rects_aabb()
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    coord::Coord,
+    empty_spaces::EmptySpacesProviderTrait,
+    finders_interface::PackingResult,
+    rect_structs::{OutputRect, RectWH, RectXYWH},
+};
+
+/// Renders a packing to an SVG image, for finding out why a layout looks the way it does.
+/// The bin is drawn as an outline, the rectangles as labeled boxes,
+/// and the free spaces, if any were added, as dashed outlines.
+#[derive(Clone, Debug, Default)]
+pub struct SvgExport<T: Coord = i32> {
+    bin: RectWH<T>,
+    rects: Vec<(RectXYWH<T>, String)>,
+    free_spaces: Vec<RectXYWH<T>>,
+}
+
+impl<T: Coord> SvgExport<T> {
+    pub fn new(bin: RectWH<T>) -> Self {
+        Self {
+            bin,
+            rects: Vec::new(),
+            free_spaces: Vec::new(),
+        }
+    }
+
+    /// The bin of `result`, with every placed rectangle labeled with its index.
+    pub fn from_result<R: OutputRect<T>>(result: &PackingResult<R, T>) -> Self {
+        result
+            .placements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, placement)| Some((index, placement.as_ref()?.get_xywh())))
+            .fold(Self::new(result.bin), |svg, (index, rect)| {
+                svg.with_rect(rect, index.to_string())
+            })
+    }
+
+    pub fn with_rect(mut self, rect: RectXYWH<T>, label: impl Into<String>) -> Self {
+        self.rects.push((rect, label.into()));
+        self
+    }
+
+    /// Adds the free spaces of a packer, e.g. from `EmptySpaces::get_spaces`.
+    pub fn with_free_spaces<P: EmptySpacesProviderTrait<T>>(mut self, spaces: &P) -> Self {
+        self.free_spaces
+            .extend((0..spaces.get_count()).map(|i| spaces.get(i)));
+        self
+    }
+
+    /// Returns the SVG document.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out)
+            .expect("formatting an SVG document into a `String` failed");
+        out
+    }
+
+    fn write(&self, out: &mut impl Write) -> fmt::Result {
+        let w = to_f64(self.bin.w);
+        let h = to_f64(self.bin.h);
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#
+        )?;
+        writeln!(
+            out,
+            r##"<rect x="0" y="0" width="{w}" height="{h}" fill="#f8f8f8" stroke="#000" stroke-width="1"/>"##
+        )?;
+
+        for (i, (rect, label)) in self.rects.iter().enumerate() {
+            let [x, y, w, h] = [rect.x, rect.y, rect.w, rect.h].map(to_f64);
+            // Spread the colors around the hue circle, so that neighbours differ.
+            let hue = (i * 137) % 360;
+            let font_size = (w.min(h) / 2.0).min(12.0);
+
+            writeln!(
+                out,
+                r##"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="hsl({hue}, 60%, 75%)" stroke="#333" stroke-width="0.5"/>"##
+            )?;
+            writeln!(
+                out,
+                r#"<text x="{}" y="{}" font-size="{font_size}" font-family="sans-serif" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                x + w / 2.0,
+                y + h / 2.0,
+                escape(label)
+            )?;
+        }
+
+        for space in &self.free_spaces {
+            let [x, y, w, h] = [space.x, space.y, space.w, space.h].map(to_f64);
+
+            writeln!(
+                out,
+                r##"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="none" stroke="#d00" stroke-width="0.5" stroke-dasharray="2 2"/>"##
+            )?;
+        }
+
+        writeln!(out, "</svg>")
+    }
+}
+
+fn to_f64<T: Coord>(value: T) -> f64 {
+    T::area_to_f64(value.to_area())
+}
+
+/// Escapes the characters that have a meaning in XML text.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
+};
+
+use crate::{
+    finders_interface::PackingResult,
+    rect_structs::{OutputRect, RectWH, RectXYWH},
+};
+
+/// The two layouts of TexturePacker's JSON, which differ in how `frames` is stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonFormat {
+    /// An object keyed by the frame names, e.g. for Phaser and PixiJS.
+    #[default]
+    Hash,
+    /// An array of frames with a `filename` each.
+    Array,
+}
+
+/// Where a sprite with transparent borders cut off came from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Trim {
+    /// Where the packed part starts within the original image.
+    pub x: i32,
+    pub y: i32,
+    /// The size of the original image.
+    pub source_size: RectWH,
+}
+
+/// A named sprite in a `TexturePackerAtlas`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub name: String,
+    /// The area covered in the atlas image. If `rotated` is set,
+    /// `w` and `h` are swapped relative to the sprite.
+    pub rect: RectXYWH,
+    /// Whether the sprite was rotated by 90 degrees clockwise.
+    pub rotated: bool,
+    /// `None` if the whole image was packed.
+    pub trim: Option<Trim>,
+}
+
+/// An atlas in TexturePacker's JSON format, as read by Phaser, PixiJS and Cocos.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TexturePackerAtlas {
+    /// The file name of the atlas image.
+    pub image: String,
+    pub size: RectWH,
+    pub frames: Vec<Frame>,
+}
+
+impl TexturePackerAtlas {
+    pub fn new(image: impl Into<String>, size: RectWH) -> Self {
+        Self {
+            image: image.into(),
+            size,
+            frames: Vec::new(),
+        }
+    }
+
+    /// The bin of `result`, with every placed rectangle named after its entry in `names`.
+    /// Rectangles that weren't placed are left out.
+    pub fn from_result<R: OutputRect>(
+        image: impl Into<String>,
+        result: &PackingResult<R>,
+        names: &[impl AsRef<str>],
+    ) -> Self {
+        result
+            .placements
+            .iter()
+            .zip(names)
+            .filter_map(|(placement, name)| Some((placement.as_ref()?, name)))
+            .fold(Self::new(image, result.bin), |atlas, (placement, name)| {
+                atlas.with_frame(Frame {
+                    name: name.as_ref().to_owned(),
+                    rect: placement.get_xywh(),
+                    rotated: placement.flipped(),
+                    trim: None,
+                })
+            })
+    }
+
+    /// Every sprite of an atlas built by `AtlasBuilder`.
+    #[cfg(feature = "png")]
+    pub fn from_image_atlas(
+        image: impl Into<String>,
+        atlas: &crate::image_atlas::ImageAtlas,
+    ) -> Self {
+        let size = RectWH::new(atlas.image.width as i32, atlas.image.height as i32);
+
+        atlas
+            .sprites
+            .iter()
+            .fold(Self::new(image, size), |tp, sprite| {
+                tp.with_frame(Frame {
+                    name: sprite.name.clone(),
+                    rect: sprite.rect,
+                    rotated: sprite.rotated,
+                    trim: None,
+                })
+            })
+    }
+
+    pub fn with_frame(mut self, frame: Frame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    /// Writes the atlas in the given layout.
+    pub fn to_json(&self, format: JsonFormat) -> String {
+        let frames = self.frames.iter().map(RawFrame::from).collect();
+
+        let file = RawFile {
+            frames: RawFrames { format, frames },
+            meta: RawMeta {
+                app: Some(env!("CARGO_PKG_NAME").to_owned()),
+                version: Some("1.0".to_owned()),
+                image: self.image.clone(),
+                format: Some("RGBA8888".to_owned()),
+                size: self.size.into(),
+                scale: Some("1".to_owned()),
+            },
+        };
+
+        serde_json::to_string_pretty(&file).expect(
+            "the frames of the hash layout are keyed by their file names, which are strings",
+        )
+    }
+
+    /// Reads an atlas in either layout, keeping the frames in the order they're stored in.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let file: RawFile = serde_json::from_str(json)?;
+
+        Ok(Self {
+            image: file.meta.image,
+            size: file.meta.size.into(),
+            frames: file.frames.frames.into_iter().map(Frame::from).collect(),
+        })
+    }
+}
+
+// Mirrors of the file format.
+
+#[derive(Serialize, Deserialize)]
+struct RawFile {
+    frames: RawFrames,
+    meta: RawMeta,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawMeta {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    app: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    version: Option<String>,
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    format: Option<String>,
+    size: RawSize,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    scale: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct RawSize {
+    w: i32,
+    h: i32,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct RawRect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFrame {
+    /// Only stored inline in the array layout.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    filename: Option<String>,
+    /// The sprite as it was before rotating it.
+    frame: RawRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<RawRect>,
+    source_size: Option<RawSize>,
+}
+
+/// The frames, along with their names, in the order they're stored in.
+struct RawFrames {
+    format: JsonFormat,
+    frames: Vec<RawFrame>,
+}
+
+impl From<RectWH> for RawSize {
+    fn from(value: RectWH) -> Self {
+        Self {
+            w: value.w,
+            h: value.h,
+        }
+    }
+}
+
+impl From<RawSize> for RectWH {
+    fn from(value: RawSize) -> Self {
+        Self::new(value.w, value.h)
+    }
+}
+
+impl From<&Frame> for RawFrame {
+    fn from(frame: &Frame) -> Self {
+        let RectXYWH { x, y, w, h } = frame.rect;
+        // TexturePacker stores the size of the sprite, not the area it covers.
+        let (w, h) = if frame.rotated { (h, w) } else { (w, h) };
+
+        let trim = frame.trim.unwrap_or(Trim {
+            x: 0,
+            y: 0,
+            source_size: RectWH::new(w, h),
+        });
+
+        Self {
+            filename: Some(frame.name.clone()),
+            frame: RawRect { x, y, w, h },
+            rotated: frame.rotated,
+            trimmed: frame.trim.is_some(),
+            sprite_source_size: Some(RawRect {
+                x: trim.x,
+                y: trim.y,
+                w,
+                h,
+            }),
+            source_size: Some(trim.source_size.into()),
+        }
+    }
+}
+
+impl From<RawFrame> for Frame {
+    fn from(raw: RawFrame) -> Self {
+        let RawRect { x, y, w, h } = raw.frame;
+        let (w, h) = if raw.rotated { (h, w) } else { (w, h) };
+
+        let trim = match (raw.trimmed, raw.sprite_source_size, raw.source_size) {
+            (true, Some(sprite), Some(source)) => Some(Trim {
+                x: sprite.x,
+                y: sprite.y,
+                source_size: source.into(),
+            }),
+            _ => None,
+        };
+
+        Self {
+            name: raw.filename.unwrap_or_default(),
+            rect: RectXYWH::new(x, y, w, h),
+            rotated: raw.rotated,
+            trim,
+        }
+    }
+}
+
+impl Serialize for RawFrames {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.format {
+            JsonFormat::Hash => serializer.collect_map(self.frames.iter().map(|frame| {
+                let name = frame.filename.as_deref().unwrap_or_default();
+
+                (
+                    name,
+                    RawFrame {
+                        filename: None,
+                        ..*frame
+                    },
+                )
+            })),
+            JsonFormat::Array => serializer.collect_seq(&self.frames),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawFrames {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FramesVisitor;
+
+        impl<'de> Visitor<'de> for FramesVisitor {
+            type Value = RawFrames;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object or an array of frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+
+                while let Some((name, frame)) = map.next_entry::<String, RawFrame>()? {
+                    frames.push(RawFrame {
+                        filename: Some(name),
+                        ..frame
+                    });
+                }
+
+                Ok(RawFrames {
+                    format: JsonFormat::Hash,
+                    frames,
+                })
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut frames = Vec::new();
+
+                while let Some(frame) = seq.next_element::<RawFrame>()? {
+                    if frame.filename.is_none() {
+                        return Err(de::Error::missing_field("filename"));
+                    }
+
+                    frames.push(frame);
+                }
+
+                Ok(RawFrames {
+                    format: JsonFormat::Array,
+                    frames,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(FramesVisitor)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    coord::{self, Coord},
+    finders_interface::{Input, PackingResult},
+    rect_structs::{OutputRect, RectXYWH},
+};
+
+/// A rule broken by a packing, see `validate_packing`.
+/// Indices refer to the rectangles in the order they were passed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The rectangle sticks out of the bin, or into its edge margin.
+    OutsideBin { index: usize },
+    /// The two rectangles overlap.
+    Overlap { first: usize, second: usize },
+    /// The two rectangles don't overlap, but are closer than the spacing
+    /// and their padding allow.
+    TooClose { first: usize, second: usize },
+    /// The rectangle overlaps the reserved region at `reserved`,
+    /// or is closer to it than the spacing and its padding allow.
+    Reserved { index: usize, reserved: usize },
+}
+
+/// Checks the result of packing with `input`, and returns every rule it breaks.
+/// An empty list means that the rectangles are inside the bin, and that the
+/// padding, spacing and edge margin are kept around all of them.
+///
+/// Every pair of rectangles is checked, so this is meant for tests and asset pipelines,
+/// rather than for running after every packing.
+pub fn validate_packing<R: OutputRect<T>, F, G, T: Coord>(
+    result: &PackingResult<R, T>,
+    input: &Input<F, G, T>,
+) -> Vec<Violation> {
+    let spacing = input.padding.spacing;
+    let edge_margin = input.padding.edge_margin;
+
+    // Every rectangle along with the area it keeps free, the same way the packers see it.
+    let placed = result
+        .placements
+        .iter()
+        .enumerate()
+        .filter_map(|(index, placement)| {
+            let rect = placement.as_ref()?.get_xywh();
+            let padding = input.rect_padding.get(index).copied().unwrap_or(T::ZERO);
+
+            // Saturating, so that unsigned coordinates of a broken packing don't underflow.
+            let padded = RectXYWH::new(
+                coord::sub_or_zero(rect.x, padding),
+                coord::sub_or_zero(rect.y, padding),
+                rect.w + T::TWO * padding + spacing,
+                rect.h + T::TWO * padding + spacing,
+            );
+
+            Some((index, rect, padding, padded))
+        })
+        .collect::<Vec<_>>();
+
+    let mut violations = Vec::new();
+
+    for (i, &(index, rect, padding, padded)) in placed.iter().enumerate() {
+        let inside = rect.x >= padding + edge_margin
+            && rect.y >= padding + edge_margin
+            && rect.x + rect.w + padding + edge_margin <= result.bin.w
+            && rect.y + rect.h + padding + edge_margin <= result.bin.h;
+
+        if !inside {
+            violations.push(Violation::OutsideBin { index });
+        }
+
+        for &(other, other_rect, _, other_padded) in &placed[i + 1..] {
+            if rect.intersects(other_rect) {
+                violations.push(Violation::Overlap {
+                    first: index,
+                    second: other,
+                });
+            } else if padded.intersects(other_padded) {
+                violations.push(Violation::TooClose {
+                    first: index,
+                    second: other,
+                });
+            }
+        }
+
+        for (reserved, region) in input.reserved.iter().enumerate() {
+            let kept = RectXYWH::new(region.x, region.y, region.w + spacing, region.h + spacing);
+
+            if padded.intersects(kept) {
+                violations.push(Violation::Reserved { index, reserved });
+            }
+        }
+    }
+
+    violations
+}